@@ -0,0 +1,130 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::files::{File, FileUploadOptions};
+use crate::ids::{FileId, TaskId, ThreadId};
+use crate::threads::{ThreadRunOptions, ThreadRunResponse};
+use crate::types::TaskSubmissionOptions;
+use std::path::Path;
+
+/// File purposes that may be attached to tasks and thread runs.
+pub const ATTACHMENT_PURPOSES: &[&str] = &["assistants", "attachments"];
+
+/// Purpose used when uploading files to attach them.
+pub const DEFAULT_ATTACHMENT_PURPOSE: &str = "assistants";
+
+impl TaskForceAI {
+    /// Checks that every referenced file exists and has an attachable purpose.
+    pub(crate) async fn validate_file_attachments(
+        &self,
+        file_ids: &[FileId],
+    ) -> Result<(), TaskForceAIError> {
+        for file_id in file_ids {
+            let file = self.get_file(file_id).await?;
+            if !ATTACHMENT_PURPOSES.contains(&file.purpose.as_str()) {
+                return Err(self.error(TaskForceAIError::InvalidAttachment(format!(
+                    "file {} has purpose \"{}\", expected one of {:?}",
+                    file.id, file.purpose, ATTACHMENT_PURPOSES
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Uploads local files so they can be attached to tasks and thread runs.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.upload_attachments",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn upload_attachments<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+    ) -> Result<Vec<File>, TaskForceAIError> {
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let options = FileUploadOptions {
+                purpose: Some(DEFAULT_ATTACHMENT_PURPOSE.to_string()),
+                ..Default::default()
+            };
+            files.push(self.upload_file_from_path(path, Some(options)).await?);
+        }
+        Ok(files)
+    }
+
+    /// Uploads local files and submits a task with them attached.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.submit_task_with_files",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn submit_task_with_files<P: AsRef<Path>>(
+        &self,
+        prompt: &str,
+        paths: &[P],
+        options: Option<TaskSubmissionOptions>,
+    ) -> Result<TaskId, TaskForceAIError> {
+        // Fail before uploading anything that would be left behind
+        let mut options = options.unwrap_or_default();
+        self.check_task(prompt, Some(&options))?;
+        self.validate_model(&options).await?;
+        if let Some(file_ids) = &options.file_ids {
+            self.validate_file_attachments(file_ids).await?;
+        }
+
+        let files = self.upload_attachments(paths).await?;
+        options
+            .file_ids
+            .get_or_insert_with(Vec::new)
+            .extend(files.into_iter().map(|f| f.id));
+        self.send_task(prompt, Some(options)).await
+    }
+
+    /// Uploads local files and runs a prompt in a thread with them attached.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.run_in_thread_with_files",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn run_in_thread_with_files<P: AsRef<Path>>(
+        &self,
+        thread_id: ThreadId,
+        paths: &[P],
+        mut options: ThreadRunOptions,
+    ) -> Result<ThreadRunResponse, TaskForceAIError> {
+        // Fail before uploading anything that would be left behind
+        self.check_thread_run(&options)?;
+        if let Some(file_ids) = &options.file_ids {
+            self.validate_file_attachments(file_ids).await?;
+        }
+
+        let files = self.upload_attachments(paths).await?;
+        options
+            .file_ids
+            .get_or_insert_with(Vec::new)
+            .extend(files.into_iter().map(|f| f.id));
+        let response = self.send_thread_run(thread_id, options).await?;
+        self.invalidate_cached_thread(thread_id).await;
+        Ok(response)
+    }
+}
@@ -0,0 +1,183 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::ids::{MessageId, ThreadId};
+use crate::threads::{CreateThreadOptions, Thread};
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
+
+/// Metadata key holding the ID of the thread a branch was forked from.
+pub const PARENT_THREAD_KEY: &str = "parent_thread_id";
+/// Metadata key holding the ID of the parent message the branch was forked at.
+pub const BRANCH_MESSAGE_KEY: &str = "branch_message_id";
+/// Metadata key holding the ID of the original thread at the top of the tree.
+pub const ROOT_THREAD_KEY: &str = "root_thread_id";
+
+/// Options for [`TaskForceAI::fork_thread`].
+#[derive(Debug, Clone, Default)]
+pub struct ForkThreadOptions {
+    /// Title of the new thread (default: the parent's title)
+    pub title: Option<String>,
+    /// Extra metadata for the new thread; the lineage keys are always set
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// A thread and the branches forked from it.
+#[derive(Debug, Clone)]
+pub struct ThreadBranch {
+    pub thread: Thread,
+    /// The parent message this branch was forked at; `None` for the root
+    pub branched_at: Option<MessageId>,
+    pub children: Vec<ThreadBranch>,
+}
+
+impl ThreadBranch {
+    /// Number of threads in this subtree, including this one.
+    pub fn thread_count(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(ThreadBranch::thread_count)
+            .sum::<usize>()
+    }
+
+    /// Every path from this thread down to a leaf, as lists of thread IDs.
+    pub fn paths(&self) -> Vec<Vec<ThreadId>> {
+        if self.children.is_empty() {
+            return vec![vec![self.thread.id]];
+        }
+        self.children
+            .iter()
+            .flat_map(ThreadBranch::paths)
+            .map(|mut path| {
+                path.insert(0, self.thread.id);
+                path
+            })
+            .collect()
+    }
+}
+
+impl Thread {
+    /// The thread this one was forked from, if it is a branch.
+    pub fn parent_thread_id(&self) -> Option<ThreadId> {
+        self.lineage(PARENT_THREAD_KEY).map(ThreadId)
+    }
+
+    /// The parent message this thread was forked at, if it is a branch.
+    pub fn branch_message_id(&self) -> Option<MessageId> {
+        self.lineage(BRANCH_MESSAGE_KEY).map(MessageId)
+    }
+
+    /// The original thread of this thread's conversation tree.
+    pub fn root_thread_id(&self) -> ThreadId {
+        self.lineage(ROOT_THREAD_KEY).map_or(self.id, ThreadId)
+    }
+
+    fn lineage(&self, key: &str) -> Option<i64> {
+        self.metadata.as_ref()?.get(key)?.as_i64()
+    }
+}
+
+impl TaskForceAI {
+    /// Copies a thread up to and including `message_id` into a new thread, recording the
+    /// parent/child lineage in the new thread's metadata.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.fork_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn fork_thread(
+        &self,
+        thread_id: ThreadId,
+        message_id: MessageId,
+        options: Option<ForkThreadOptions>,
+    ) -> Result<Thread, TaskForceAIError> {
+        let options = options.unwrap_or_default();
+        let parent = self.get_thread(thread_id).await?;
+
+        let mut messages = Vec::new();
+        let mut found = false;
+        let mut pages = self.thread_messages(thread_id);
+        while let Some(message) = pages.try_next().await? {
+            found = message.id == message_id;
+            messages.push(message);
+            if found {
+                break;
+            }
+        }
+        if !found {
+            return Err(self.error(TaskForceAIError::Other(format!(
+                "Message {} not found in thread {}",
+                message_id, thread_id
+            ))));
+        }
+
+        let mut metadata = options.metadata.unwrap_or_default();
+        metadata.insert(PARENT_THREAD_KEY.to_string(), thread_id.into());
+        metadata.insert(BRANCH_MESSAGE_KEY.to_string(), message_id.into());
+        metadata.insert(ROOT_THREAD_KEY.to_string(), parent.root_thread_id().into());
+
+        self.create_thread(Some(CreateThreadOptions {
+            title: Some(options.title.unwrap_or(parent.title)),
+            messages: Some(messages),
+            metadata: Some(metadata),
+        }))
+        .await
+    }
+
+    /// Builds the tree of every branch in the conversation that `thread_id` belongs to,
+    /// starting from its root thread.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.thread_tree",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn thread_tree(&self, thread_id: ThreadId) -> Result<ThreadBranch, TaskForceAIError> {
+        let thread = self.get_thread(thread_id).await?;
+        let root_id = thread.root_thread_id();
+        let root = if root_id == thread.id {
+            thread
+        } else {
+            self.get_thread(root_id).await?
+        };
+
+        let mut children: HashMap<ThreadId, Vec<Thread>> = HashMap::new();
+        let mut threads = self.threads();
+        while let Some(thread) = threads.try_next().await? {
+            if thread.id != root_id && thread.root_thread_id() == root_id {
+                if let Some(parent_id) = thread.parent_thread_id() {
+                    children.entry(parent_id).or_default().push(thread);
+                }
+            }
+        }
+        Ok(build_branch(root, &mut children))
+    }
+}
+
+fn build_branch(thread: Thread, children: &mut HashMap<ThreadId, Vec<Thread>>) -> ThreadBranch {
+    let mut branches: Vec<ThreadBranch> = children
+        .remove(&thread.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| build_branch(child, children))
+        .collect();
+    branches.sort_by_key(|branch| (branch.branched_at, branch.thread.created_at));
+    ThreadBranch {
+        branched_at: thread.branch_message_id(),
+        thread,
+        children: branches,
+    }
+}
@@ -0,0 +1,186 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::usage::Usage;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BUDGET_THRESHOLD: f64 = 0.8;
+
+/// Spend observed against a [`Budget`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetStatus {
+    pub spent_cost: f64,
+    pub spent_tokens: u64,
+    pub max_cost: Option<f64>,
+    pub max_tokens: Option<u64>,
+}
+
+impl BudgetStatus {
+    /// The largest fraction of any configured limit that has been used.
+    pub fn fraction_used(&self) -> f64 {
+        let cost = self.max_cost.map(|max| ratio(self.spent_cost, max));
+        let tokens = self
+            .max_tokens
+            .map(|max| ratio(self.spent_tokens as f64, max as f64));
+        cost.into_iter().chain(tokens).fold(0.0, f64::max)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.fraction_used() >= 1.0
+    }
+}
+
+fn ratio(spent: f64, max: f64) -> f64 {
+    if max <= 0.0 {
+        f64::INFINITY
+    } else {
+        spent / max
+    }
+}
+
+type ThresholdCallback = Arc<dyn Fn(&BudgetStatus) + Send + Sync>;
+
+/// A spending limit enforced by the client.
+///
+/// Usage is only known once a task completes, so tasks already in flight when the
+/// budget runs out can overshoot it; new submissions fail with
+/// [`TaskForceAIError::BudgetExceeded`] from then on.
+#[derive(Clone, Default)]
+pub struct Budget {
+    max_cost: Option<f64>,
+    max_tokens: Option<u64>,
+    window: Option<Duration>,
+    threshold: Option<f64>,
+    on_threshold: Option<ThresholdCallback>,
+}
+
+impl Budget {
+    /// Limits total cost in USD.
+    pub fn max_cost(mut self, max_cost: f64) -> Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    /// Limits total input plus output tokens.
+    pub fn max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Applies the limits to a rolling time window instead of the client's lifetime.
+    pub fn per(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Calls `callback` once spend crosses `threshold` (a fraction, default 0.8) of the budget.
+    /// With a window, it fires again after spend drops back below the threshold.
+    pub fn on_threshold(
+        mut self,
+        threshold: f64,
+        callback: impl Fn(&BudgetStatus) + Send + Sync + 'static,
+    ) -> Self {
+        self.threshold = Some(threshold);
+        self.on_threshold = Some(Arc::new(callback));
+        self
+    }
+}
+
+impl fmt::Debug for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Budget")
+            .field("max_cost", &self.max_cost)
+            .field("max_tokens", &self.max_tokens)
+            .field("window", &self.window)
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Tracks spend against a budget over time.
+pub(crate) struct BudgetLedger {
+    budget: Budget,
+    entries: VecDeque<(Instant, u64, f64)>,
+    threshold_fired: bool,
+}
+
+impl BudgetLedger {
+    pub(crate) fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            entries: VecDeque::new(),
+            threshold_fired: false,
+        }
+    }
+
+    pub(crate) fn status(&mut self) -> BudgetStatus {
+        if let Some(window) = self.budget.window {
+            let now = Instant::now();
+            while let Some((at, _, _)) = self.entries.front() {
+                if now.duration_since(*at) < window {
+                    break;
+                }
+                self.entries.pop_front();
+            }
+        }
+
+        let (spent_tokens, spent_cost) = self
+            .entries
+            .iter()
+            .fold((0, 0.0), |(tokens, cost), (_, t, c)| (tokens + t, cost + c));
+
+        BudgetStatus {
+            spent_cost,
+            spent_tokens,
+            max_cost: self.budget.max_cost,
+            max_tokens: self.budget.max_tokens,
+        }
+    }
+
+    pub(crate) fn check(&mut self) -> Result<(), TaskForceAIError> {
+        let status = self.status();
+        if status.is_exhausted() {
+            return Err(TaskForceAIError::BudgetExceeded(format!(
+                "spent ${:.4} and {} tokens",
+                status.spent_cost, status.spent_tokens
+            )));
+        }
+        Ok(())
+    }
+
+    /// Records spend, returning the threshold callback and status if it should fire now.
+    pub(crate) fn record(&mut self, usage: &Usage) -> Option<(ThresholdCallback, BudgetStatus)> {
+        self.entries
+            .push_back((Instant::now(), usage.total_tokens(), usage.cost));
+
+        let status = self.status();
+        let threshold = self.budget.threshold.unwrap_or(DEFAULT_BUDGET_THRESHOLD);
+        if status.fraction_used() < threshold {
+            self.threshold_fired = false;
+            return None;
+        }
+        if self.threshold_fired {
+            return None;
+        }
+        self.threshold_fired = true;
+        self.budget
+            .on_threshold
+            .clone()
+            .map(|callback| (callback, status))
+    }
+}
+
+impl TaskForceAI {
+    /// Enforces a spending budget on task submissions.
+    pub fn with_budget(self, budget: Budget) -> Self {
+        self.usage.set_budget(budget);
+        self
+    }
+
+    /// Returns spend against the configured budget, if any.
+    pub fn budget_status(&self) -> Option<BudgetStatus> {
+        self.usage.budget_status()
+    }
+}
@@ -1,8 +1,21 @@
 use crate::error::TaskForceAIError;
+use crate::ids::{TaskId, ThreadId};
+use crate::metrics::{endpoint_label, MetricsRecorder, NoopMetrics};
+use crate::middleware::{Middleware, RequestContext};
+use crate::models::ModelCatalog;
+use crate::pagination::PageRequest;
+use crate::search::SearchCache;
+#[cfg(feature = "local-store")]
+use crate::store::LocalStore;
+use crate::telemetry;
+use crate::threads::{ThreadMessage, ThreadMessagesResponse};
 use crate::types::{
     SubmitTaskResponse, TaskForceAIOptions, TaskStatus, TaskStatusValue, TaskSubmissionOptions,
 };
-use std::time::Duration;
+use crate::uploads::DEFAULT_CHUNKED_UPLOAD_THRESHOLD;
+use crate::usage::UsageTracker;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 pub const DEFAULT_BASE_URL: &str = "https://taskforceai.chat/api/developer";
@@ -17,6 +30,14 @@ pub struct TaskForceAI {
     pub(crate) timeout: Duration,
     pub(crate) mock_mode: bool,
     pub(crate) client: reqwest::Client,
+    pub(crate) middleware: Vec<Arc<dyn Middleware>>,
+    pub(crate) metrics: Arc<dyn MetricsRecorder>,
+    pub(crate) usage: Arc<UsageTracker>,
+    pub(crate) chunked_upload_threshold: u64,
+    pub(crate) models: ModelCatalog,
+    pub(crate) search_index: SearchCache,
+    #[cfg(feature = "local-store")]
+    pub(crate) local_store: Option<LocalStore>,
 }
 
 impl TaskForceAI {
@@ -44,38 +65,184 @@ impl TaskForceAI {
             timeout,
             mock_mode,
             client,
+            middleware: Vec::new(),
+            metrics: Arc::new(NoopMetrics),
+            usage: Arc::new(UsageTracker::default()),
+            chunked_upload_threshold: options
+                .chunked_upload_threshold
+                .unwrap_or(DEFAULT_CHUNKED_UPLOAD_THRESHOLD),
+            models: ModelCatalog::default(),
+            search_index: SearchCache::default(),
+            #[cfg(feature = "local-store")]
+            local_store: None,
         })
     }
 
-    pub(crate) async fn request<T>(
+    /// Appends a middleware to the request chain.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sets the recorder that receives latency, throughput and error metrics.
+    pub fn with_metrics(mut self, recorder: impl MetricsRecorder + 'static) -> Self {
+        self.metrics = Arc::new(recorder);
+        self
+    }
+
+    /// Records an SDK-originated error with the metrics recorder and returns it.
+    pub(crate) fn error(&self, error: TaskForceAIError) -> TaskForceAIError {
+        self.metrics.record_error(error.kind());
+        error
+    }
+
+    /// Serves a page of a thread's messages from the local store, if the thread is stored there.
+    #[cfg(feature = "local-store")]
+    pub(crate) async fn cached_thread_messages(
         &self,
-        method: reqwest::Method,
-        path: &str,
-        body: Option<serde_json::Value>,
-    ) -> Result<T, TaskForceAIError>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        if self.mock_mode {
-            return self.mock_response(path, &method);
+        thread_id: ThreadId,
+        page: &PageRequest,
+    ) -> Option<ThreadMessagesResponse> {
+        if page.cursor.is_some() {
+            return None;
         }
+        let store = self.local_store.as_ref()?;
+        let (limit, offset) = (page.limit, page.offset);
+        let (messages, total) = store
+            .run_blocking(move |store| store.messages(thread_id, limit, offset))
+            .await
+            .ok()
+            .flatten()?;
+        Some(ThreadMessagesResponse {
+            messages,
+            total,
+            next_cursor: None,
+        })
+    }
 
-        let url = format!("{}{}", self.base_url, path);
-        let mut request = self.client.request(method, &url);
+    #[cfg(not(feature = "local-store"))]
+    pub(crate) async fn cached_thread_messages(
+        &self,
+        _thread_id: ThreadId,
+        _page: &PageRequest,
+    ) -> Option<ThreadMessagesResponse> {
+        None
+    }
+
+    /// Drops a thread from the local store so the next read goes to the server.
+    #[cfg(feature = "local-store")]
+    pub(crate) async fn invalidate_cached_thread(&self, thread_id: ThreadId) {
+        if let Some(store) = &self.local_store {
+            let _ = store
+                .run_blocking(move |store| store.remove_thread(thread_id))
+                .await;
+        }
+    }
+
+    #[cfg(not(feature = "local-store"))]
+    pub(crate) async fn invalidate_cached_thread(&self, _thread_id: ThreadId) {}
 
+    /// Adds new messages to a thread's local copy, if the thread is stored.
+    #[cfg(feature = "local-store")]
+    pub(crate) async fn append_cached_messages(
+        &self,
+        thread_id: ThreadId,
+        messages: Vec<ThreadMessage>,
+    ) {
+        if let Some(store) = &self.local_store {
+            let _ = store
+                .run_blocking(move |store| store.append_messages(thread_id, &messages))
+                .await;
+        }
+    }
+
+    #[cfg(not(feature = "local-store"))]
+    pub(crate) async fn append_cached_messages(
+        &self,
+        _thread_id: ThreadId,
+        _messages: Vec<ThreadMessage>,
+    ) {
+    }
+
+    /// Sends a request through the middleware chain, returning the response on success.
+    pub(crate) async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, TaskForceAIError> {
+        self.send_expecting(request, &[]).await
+    }
+
+    /// Like [`send`](Self::send), but API errors with one of `expected` statuses are
+    /// returned without being reported to metrics or middleware as failures.
+    pub(crate) async fn send_expecting(
+        &self,
+        request: reqwest::RequestBuilder,
+        expected: &[u16],
+    ) -> Result<reqwest::Response, TaskForceAIError> {
+        let mut request = request;
         if !self.api_key.is_empty() {
             request = request.header("x-api-key", &self.api_key);
         }
-
         request = request.header("X-SDK-Language", "rust");
 
-        if let Some(b) = body {
-            request = request.json(&b);
+        let mut request = request.build()?;
+        telemetry::inject_trace_context(&mut request);
+        let mut context = RequestContext::new(&request);
+
+        let result = self.execute(request, &mut context).await;
+
+        let status = match &result {
+            Ok(response) => Some(response.status().as_u16()),
+            Err(TaskForceAIError::Api { status, .. }) => Some(status.as_u16()),
+            Err(_) => None,
+        };
+        self.metrics.record_request(
+            &context.method,
+            &self.endpoint(&context.url),
+            status,
+            context.elapsed(),
+        );
+
+        let expected_error = matches!(
+            &result,
+            Err(TaskForceAIError::Api { status, .. }) if expected.contains(&status.as_u16())
+        );
+        if let (Err(e), false) = (&result, expected_error) {
+            self.metrics.record_error(e.kind());
+            for middleware in self.middleware.iter().rev() {
+                middleware.on_error(&context, e).await;
+            }
         }
+        result
+    }
 
-        let response = request.send().await?;
-        let status = response.status();
+    fn endpoint(&self, url: &reqwest::Url) -> String {
+        let path = url
+            .as_str()
+            .strip_prefix(&self.base_url)
+            .unwrap_or_else(|| url.path());
+        endpoint_label(path.split('?').next().unwrap_or_default())
+    }
+
+    async fn execute(
+        &self,
+        mut request: reqwest::Request,
+        context: &mut RequestContext,
+    ) -> Result<reqwest::Response, TaskForceAIError> {
+        for middleware in &self.middleware {
+            middleware.before_request(&mut request).await?;
+        }
+        *context = RequestContext::new(&request);
+        telemetry::record("endpoint", context.url.path());
+
+        let response = self.client.execute(request).await?;
+        telemetry::record("http.status_code", response.status().as_u16());
 
+        for middleware in self.middleware.iter().rev() {
+            middleware.after_response(context, &response).await?;
+        }
+
+        let status = response.status();
         if !status.is_success() {
             let message = response
                 .text()
@@ -84,7 +251,46 @@ impl TaskForceAI {
             return Err(TaskForceAIError::Api { status, message });
         }
 
-        Ok(response.json().await?)
+        Ok(response)
+    }
+
+    pub(crate) async fn request<T>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<T, TaskForceAIError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.request_expecting(method, path, body, &[]).await
+    }
+
+    /// Like [`request`](Self::request), tolerating the `expected` error statuses as in
+    /// [`send_expecting`](Self::send_expecting).
+    pub(crate) async fn request_expecting<T>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+        expected: &[u16],
+    ) -> Result<T, TaskForceAIError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if self.mock_mode {
+            return self.mock_response(path, &method);
+        }
+
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = self.client.request(method, &url);
+
+        if let Some(b) = body {
+            request = request.json(&b);
+        }
+
+        let response = self.send_expecting(request, expected).await?;
+        response.json().await.map_err(|e| self.error(e.into()))
     }
 
     fn mock_response<T>(&self, path: &str, method: &reqwest::Method) -> Result<T, TaskForceAIError>
@@ -106,15 +312,57 @@ impl TaskForceAI {
         Ok(serde_json::from_value(val)?)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.submit_task",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn submit_task(
         &self,
         prompt: &str,
         options: Option<TaskSubmissionOptions>,
-    ) -> Result<String, TaskForceAIError> {
+    ) -> Result<TaskId, TaskForceAIError> {
+        self.check_task(prompt, options.as_ref())?;
+        if let Some(opts) = &options {
+            self.validate_model(opts).await?;
+        }
+        if let Some(file_ids) = options.as_ref().and_then(|o| o.file_ids.as_ref()) {
+            self.validate_file_attachments(file_ids).await?;
+        }
+        self.send_task(prompt, options).await
+    }
+
+    /// Checks a task locally, without any request: prompt, options, images and budget.
+    pub(crate) fn check_task(
+        &self,
+        prompt: &str,
+        options: Option<&TaskSubmissionOptions>,
+    ) -> Result<(), TaskForceAIError> {
         if prompt.trim().is_empty() {
-            return Err(TaskForceAIError::EmptyPrompt);
+            return Err(self.error(TaskForceAIError::EmptyPrompt));
         }
+        if let Some(opts) = options {
+            opts.validate().map_err(|e| self.error(e))?;
+        }
+        for image in options.iter().flat_map(|o| o.images.iter().flatten()) {
+            image.validate().map_err(|e| self.error(e))?;
+        }
+        self.usage.check_budget().map_err(|e| self.error(e))
+    }
 
+    /// Submits a task that has already been checked.
+    pub(crate) async fn send_task(
+        &self,
+        prompt: &str,
+        options: Option<TaskSubmissionOptions>,
+    ) -> Result<TaskId, TaskForceAIError> {
         let mut body = serde_json::json!({ "prompt": prompt });
         if let Some(opts) = options {
             let images = opts.images.clone();
@@ -131,43 +379,88 @@ impl TaskForceAI {
         let response: SubmitTaskResponse = self
             .request(reqwest::Method::POST, "/run", Some(body))
             .await?;
+        telemetry::record("task_id", &response.task_id);
         Ok(response.task_id)
     }
 
-    pub async fn get_task_status(&self, task_id: &str) -> Result<TaskStatus, TaskForceAIError> {
-        if task_id.trim().is_empty() {
-            return Err(TaskForceAIError::EmptyTaskId);
-        }
-        self.request(reqwest::Method::GET, &format!("/status/{}", task_id), None)
-            .await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.get_task_status",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                task_id = %task_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn get_task_status(&self, task_id: &TaskId) -> Result<TaskStatus, TaskForceAIError> {
+        let segment = task_id.path_segment().map_err(|e| self.error(e))?;
+        let status: TaskStatus = self
+            .request(reqwest::Method::GET, &format!("/status/{}", segment), None)
+            .await?;
+        self.usage.record(&status);
+        Ok(status)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.wait_for_completion",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                task_id = %task_id,
+                poll_attempts = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn wait_for_completion(
         &self,
-        task_id: &str,
+        task_id: &TaskId,
         poll_interval: Option<Duration>,
         max_attempts: Option<u32>,
     ) -> Result<TaskStatus, TaskForceAIError> {
         let interval = poll_interval.unwrap_or(Duration::from_millis(DEFAULT_POLL_INTERVAL_MS));
         let max = max_attempts.unwrap_or(DEFAULT_MAX_POLL_ATTEMPTS);
 
-        for _ in 0..max {
+        for attempt in 1..=max {
+            telemetry::record("poll_attempts", attempt);
             let status = self.get_task_status(task_id).await?;
             match status.status {
-                TaskStatusValue::Completed => return Ok(status),
+                TaskStatusValue::Completed => {
+                    self.metrics.record_poll_attempts(attempt);
+                    return Ok(status);
+                }
                 TaskStatusValue::Failed => {
-                    return Err(TaskForceAIError::TaskFailed(
+                    self.metrics.record_poll_attempts(attempt);
+                    return Err(self.error(TaskForceAIError::TaskFailed(
                         status.error.unwrap_or_else(|| "Unknown error".to_string()),
-                    ))
+                    )));
                 }
                 TaskStatusValue::Processing => (),
             }
             sleep(interval).await;
         }
 
-        Err(TaskForceAIError::Timeout)
+        self.metrics.record_poll_attempts(max);
+        Err(self.error(TaskForceAIError::Timeout))
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.run_task",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn run_task(
         &self,
         prompt: &str,
@@ -175,8 +468,13 @@ impl TaskForceAI {
         poll_interval: Option<Duration>,
         max_attempts: Option<u32>,
     ) -> Result<TaskStatus, TaskForceAIError> {
+        let started_at = Instant::now();
         let task_id = self.submit_task(prompt, options).await?;
-        self.wait_for_completion(&task_id, poll_interval, max_attempts)
-            .await
+        telemetry::record("task_id", &task_id);
+        let status = self
+            .wait_for_completion(&task_id, poll_interval, max_attempts)
+            .await?;
+        self.metrics.record_task_duration(started_at.elapsed());
+        Ok(status)
     }
 }
@@ -0,0 +1,380 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::ids::{MessageId, ThreadId};
+use crate::messages::{MessageContent, Role};
+use crate::stream::TaskStatusStream;
+use crate::threads::{ThreadMessage, ThreadRunOptions};
+use crate::types::{TaskStatus, TaskStatusValue, TaskSubmissionOptions};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Rough number of characters per token used to estimate message sizes.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimates how many tokens a message occupies in the model's context.
+pub fn estimate_tokens(message: &ThreadMessage) -> usize {
+    message
+        .content
+        .text()
+        .chars()
+        .count()
+        .div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Decides which part of a conversation's history is sent to the model with each turn.
+///
+/// Strategies receive the full local history, oldest first, and return the messages to
+/// use as context. They may drop, reorder or synthesize messages (e.g. a summary).
+#[async_trait]
+pub trait ContextStrategy: Send + Sync {
+    async fn select(
+        &self,
+        messages: &[ThreadMessage],
+    ) -> Result<Vec<ThreadMessage>, TaskForceAIError>;
+}
+
+/// Sends the entire history. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepAll;
+
+#[async_trait]
+impl ContextStrategy for KeepAll {
+    async fn select(
+        &self,
+        messages: &[ThreadMessage],
+    ) -> Result<Vec<ThreadMessage>, TaskForceAIError> {
+        Ok(messages.to_vec())
+    }
+}
+
+/// Keeps the most recent messages that fit within an estimated token budget.
+#[derive(Debug, Clone, Copy)]
+pub struct TrimToTokens {
+    pub max_tokens: usize,
+}
+
+impl TrimToTokens {
+    pub fn new(max_tokens: usize) -> Self {
+        Self { max_tokens }
+    }
+}
+
+#[async_trait]
+impl ContextStrategy for TrimToTokens {
+    async fn select(
+        &self,
+        messages: &[ThreadMessage],
+    ) -> Result<Vec<ThreadMessage>, TaskForceAIError> {
+        let mut used = 0;
+        let keep = messages
+            .iter()
+            .rev()
+            .take_while(|message| {
+                used += estimate_tokens(message);
+                used <= self.max_tokens
+            })
+            .count();
+        Ok(messages[messages.len() - keep..].to_vec())
+    }
+}
+
+type Summarizer = Arc<dyn Fn(&[ThreadMessage]) -> String + Send + Sync>;
+
+/// Replaces all but the most recent turns with a single system message summarizing them.
+#[derive(Clone)]
+pub struct SummarizeOlder {
+    keep_recent: usize,
+    summarizer: Summarizer,
+}
+
+impl SummarizeOlder {
+    /// Keeps the last `keep_recent` messages verbatim and passes the older ones to `summarizer`.
+    pub fn new(
+        keep_recent: usize,
+        summarizer: impl Fn(&[ThreadMessage]) -> String + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            keep_recent,
+            summarizer: Arc::new(summarizer),
+        }
+    }
+}
+
+#[async_trait]
+impl ContextStrategy for SummarizeOlder {
+    async fn select(
+        &self,
+        messages: &[ThreadMessage],
+    ) -> Result<Vec<ThreadMessage>, TaskForceAIError> {
+        if messages.len() <= self.keep_recent {
+            return Ok(messages.to_vec());
+        }
+        let (older, recent) = messages.split_at(messages.len() - self.keep_recent);
+        let summary = ThreadMessage {
+            id: MessageId::default(),
+            thread_id: older[0].thread_id,
+            role: Role::System,
+            content: format!(
+                "Summary of earlier conversation: {}",
+                (self.summarizer)(older)
+            )
+            .into(),
+            created_at: older[older.len() - 1].created_at,
+            metadata: None,
+        };
+        Ok(std::iter::once(summary)
+            .chain(recent.iter().cloned())
+            .collect())
+    }
+}
+
+/// Always keeps system messages and applies the inner strategy to everything else.
+pub struct PinSystemMessages<S> {
+    inner: S,
+}
+
+impl<S: ContextStrategy> PinSystemMessages<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl<S: ContextStrategy> ContextStrategy for PinSystemMessages<S> {
+    async fn select(
+        &self,
+        messages: &[ThreadMessage],
+    ) -> Result<Vec<ThreadMessage>, TaskForceAIError> {
+        let (mut pinned, rest): (Vec<_>, Vec<_>) = messages
+            .iter()
+            .cloned()
+            .partition(|message| message.role == Role::System);
+        pinned.extend(self.inner.select(&rest).await?);
+        Ok(pinned)
+    }
+}
+
+pub type ConversationStream<'a> =
+    Pin<Box<dyn Stream<Item = Result<TaskStatus, TaskForceAIError>> + Send + 'a>>;
+
+/// A chat session that keeps its message history in memory.
+///
+/// A conversation is either backed by a server-side thread, in which case turns are run
+/// with [`TaskForceAI::run_in_thread`] and the history mirrors `get_thread_messages`, or
+/// purely local, in which case the history is rendered into each task prompt. Either way
+/// the [`ContextStrategy`] decides how much history accompanies each turn.
+pub struct Conversation<'a> {
+    client: &'a TaskForceAI,
+    thread_id: Option<ThreadId>,
+    messages: Vec<ThreadMessage>,
+    strategy: Box<dyn ContextStrategy>,
+    model_id: Option<String>,
+}
+
+impl<'a> Conversation<'a> {
+    /// Starts a conversation that lives only in memory.
+    pub fn local(client: &'a TaskForceAI) -> Self {
+        Self {
+            client,
+            thread_id: None,
+            messages: Vec::new(),
+            strategy: Box::new(KeepAll),
+            model_id: None,
+        }
+    }
+
+    /// Opens a conversation on an existing thread and loads its messages.
+    pub async fn for_thread(
+        client: &'a TaskForceAI,
+        thread_id: ThreadId,
+    ) -> Result<Self, TaskForceAIError> {
+        let mut conversation = Self {
+            thread_id: Some(thread_id),
+            ..Self::local(client)
+        };
+        conversation.sync().await?;
+        Ok(conversation)
+    }
+
+    /// Sets the strategy that picks the context sent with each turn.
+    pub fn with_strategy(mut self, strategy: impl ContextStrategy + 'static) -> Self {
+        self.strategy = Box::new(strategy);
+        self
+    }
+
+    /// Sets the model used for every turn.
+    pub fn with_model(mut self, model_id: impl Into<String>) -> Self {
+        self.model_id = Some(model_id.into());
+        self
+    }
+
+    /// The backing thread, if any.
+    pub fn thread_id(&self) -> Option<ThreadId> {
+        self.thread_id
+    }
+
+    /// The local copy of the conversation's messages, oldest first.
+    pub fn messages(&self) -> &[ThreadMessage] {
+        &self.messages
+    }
+
+    /// Adds a message to the local history without sending it, e.g. a system prompt.
+    pub fn push(&mut self, role: Role, content: impl Into<MessageContent>) {
+        let message = self.local_message(role, content.into());
+        self.messages.push(message);
+    }
+
+    /// Replaces the local history with the backing thread's messages.
+    /// Does nothing for local conversations.
+    pub async fn sync(&mut self) -> Result<(), TaskForceAIError> {
+        if let Some(thread_id) = self.thread_id {
+            self.messages = self.client.thread_messages(thread_id).try_collect().await?;
+        }
+        Ok(())
+    }
+
+    /// The messages the context strategy currently selects from the history.
+    pub async fn context(&self) -> Result<Vec<ThreadMessage>, TaskForceAIError> {
+        self.strategy.select(&self.messages).await
+    }
+
+    /// Sends a prompt, waits for the reply and returns the assistant's message.
+    ///
+    /// The prompt and the reply are appended to the local history; other messages added
+    /// to a backing thread meanwhile appear after the next [`sync`](Self::sync).
+    pub async fn send(&mut self, prompt: &str) -> Result<ThreadMessage, TaskForceAIError> {
+        match self.thread_id {
+            Some(thread_id) => {
+                let options = self.thread_run_options(prompt).await?;
+                let result = self
+                    .client
+                    .run_in_thread_and_wait(thread_id, options, None, None)
+                    .await?;
+                self.messages.push(ThreadMessage {
+                    id: result.prompt_message_id,
+                    ..self.local_message(Role::User, prompt.into())
+                });
+                self.messages.push(result.message.clone());
+                Ok(result.message)
+            }
+            None => {
+                let status = self
+                    .client
+                    .run_task(
+                        &self.local_prompt(prompt).await?,
+                        self.task_options(),
+                        None,
+                        None,
+                    )
+                    .await?;
+                self.push(Role::User, prompt);
+                self.push(Role::Assistant, status.result.unwrap_or_default());
+                Ok(self.messages[self.messages.len() - 1].clone())
+            }
+        }
+    }
+
+    /// Sends a prompt and streams status updates as the reply is produced.
+    ///
+    /// The prompt and the completed reply are appended to the local history as the stream
+    /// is consumed. For thread-backed conversations they carry provisional IDs until the
+    /// next [`sync`](Self::sync).
+    pub async fn send_stream(
+        &mut self,
+        prompt: &str,
+    ) -> Result<ConversationStream<'_>, TaskForceAIError> {
+        let stream: TaskStatusStream = match self.thread_id {
+            Some(thread_id) => {
+                let options = self.thread_run_options(prompt).await?;
+                self.client.run_in_thread_stream(thread_id, options).await?
+            }
+            None => {
+                self.client
+                    .run_task_stream(&self.local_prompt(prompt).await?, self.task_options())
+                    .await?
+            }
+        };
+        self.push(Role::User, prompt);
+
+        Ok(Box::pin(stream.inspect(move |update| {
+            if let Ok(status) = update {
+                if status.status == TaskStatusValue::Completed {
+                    let reply = status.result.clone().unwrap_or_default();
+                    self.push(Role::Assistant, reply);
+                }
+            }
+        })))
+    }
+
+    async fn thread_run_options(&self, prompt: &str) -> Result<ThreadRunOptions, TaskForceAIError> {
+        let context = self.context().await?;
+        // The server already has the thread's history; only override it when the strategy changed it
+        let options = if context != self.messages {
+            Some(HashMap::from([(
+                "context".to_string(),
+                serde_json::to_value(&context)?,
+            )]))
+        } else {
+            None
+        };
+        Ok(ThreadRunOptions {
+            prompt: prompt.to_string(),
+            model_id: self.model_id.clone(),
+            options,
+            ..Default::default()
+        })
+    }
+
+    fn task_options(&self) -> Option<TaskSubmissionOptions> {
+        self.model_id
+            .as_ref()
+            .map(|model_id| TaskSubmissionOptions {
+                model_id: Some(model_id.clone()),
+                ..Default::default()
+            })
+    }
+
+    /// Renders the selected context and the new prompt into a single task prompt.
+    async fn local_prompt(&self, prompt: &str) -> Result<String, TaskForceAIError> {
+        let context = self.context().await?;
+        if context.is_empty() {
+            return Ok(prompt.to_string());
+        }
+        let mut rendered = String::new();
+        for message in &context {
+            rendered.push_str(&format!("{}: {}\n\n", message.role, message.content.text()));
+        }
+        rendered.push_str(&format!("{}: {}", Role::User, prompt));
+        Ok(rendered)
+    }
+
+    fn local_message(&self, role: Role, content: MessageContent) -> ThreadMessage {
+        ThreadMessage {
+            id: MessageId(self.messages.last().map_or(1, |message| message.id.0 + 1)),
+            thread_id: self.thread_id.unwrap_or_default(),
+            role,
+            content,
+            created_at: Utc::now(),
+            metadata: None,
+        }
+    }
+}
+
+impl TaskForceAI {
+    /// Starts an in-memory [`Conversation`].
+    pub fn conversation(&self) -> Conversation<'_> {
+        Conversation::local(self)
+    }
+
+    /// Opens a [`Conversation`] on an existing thread, loading its messages.
+    pub async fn thread_conversation(
+        &self,
+        thread_id: ThreadId,
+    ) -> Result<Conversation<'_>, TaskForceAIError> {
+        Conversation::for_thread(self, thread_id).await
+    }
+}
@@ -0,0 +1,218 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::files::{File, ProgressCallback, TransferProgress};
+use crate::ids::FileId;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, TaskForceAIError>> + Send>>;
+
+/// Response header carrying the hex-encoded SHA-256 of the full file.
+pub const CHECKSUM_HEADER: &str = "x-checksum-sha256";
+
+/// Options for downloading a file to disk.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Continue a previous partial download with an HTTP Range request
+    pub resume: bool,
+    /// Receives progress updates while the file is written
+    pub progress: Option<ProgressCallback>,
+}
+
+impl TaskForceAI {
+    /// Downloads the content of a file as a stream of chunks.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.download_file_stream",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                file_id = %file_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn download_file_stream(
+        &self,
+        file_id: &FileId,
+    ) -> Result<ByteStream, TaskForceAIError> {
+        let response = self.request_file_content(file_id, 0).await?;
+        Ok(self.byte_stream(response))
+    }
+
+    /// Downloads a file to `path`, writing through a temporary `.part` file that is
+    /// renamed into place once the size and checksum have been verified.
+    ///
+    /// With `resume`, an existing `.part` file from an earlier attempt is continued
+    /// and kept if this attempt fails.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.download_file_to_path",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                file_id = %file_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn download_file_to_path(
+        &self,
+        file_id: &FileId,
+        path: impl AsRef<Path>,
+        options: Option<DownloadOptions>,
+    ) -> Result<File, TaskForceAIError> {
+        let path = path.as_ref();
+        let options = options.unwrap_or_default();
+        let partial = partial_path(path);
+
+        let result = self.download_to_partial(file_id, &partial, &options).await;
+        match result {
+            Ok(file) => {
+                tokio::fs::rename(&partial, path)
+                    .await
+                    .map_err(|e| self.error(e.into()))?;
+                Ok(file)
+            }
+            Err(e) => {
+                if !options.resume {
+                    let _ = tokio::fs::remove_file(&partial).await;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn download_to_partial(
+        &self,
+        file_id: &FileId,
+        partial: &Path,
+        options: &DownloadOptions,
+    ) -> Result<File, TaskForceAIError> {
+        let file = self.get_file(file_id).await?;
+        let expected = u64::try_from(file.bytes).ok();
+
+        let mut offset = match tokio::fs::metadata(partial).await {
+            Ok(metadata) if options.resume => metadata.len(),
+            _ => 0,
+        };
+        if expected.is_some_and(|expected| offset >= expected) {
+            offset = 0;
+        }
+
+        let response = self.request_file_content(file_id, offset).await?;
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            offset = 0;
+        }
+        let checksum = response
+            .headers()
+            .get(CHECKSUM_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_ascii_lowercase);
+
+        let mut hasher = Sha256::new();
+        let mut output = if offset > 0 {
+            let mut existing = tokio::fs::File::open(partial)
+                .await
+                .map_err(|e| self.error(e.into()))?;
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let n = existing
+                    .read(&mut buffer)
+                    .await
+                    .map_err(|e| self.error(e.into()))?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..n]);
+            }
+            tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(partial)
+                .await
+        } else {
+            tokio::fs::File::create(partial).await
+        }
+        .map_err(|e| self.error(e.into()))?;
+
+        let mut written = offset;
+        let mut chunks = self.byte_stream(response);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            output
+                .write_all(&chunk)
+                .await
+                .map_err(|e| self.error(e.into()))?;
+            written += chunk.len() as u64;
+            if let Some(progress) = &options.progress {
+                progress.report(TransferProgress {
+                    bytes_transferred: written,
+                    total_bytes: expected,
+                });
+            }
+        }
+        output.flush().await.map_err(|e| self.error(e.into()))?;
+        output.sync_all().await.map_err(|e| self.error(e.into()))?;
+
+        if let Some(expected) = expected {
+            if written != expected {
+                return Err(self.error(TaskForceAIError::Other(format!(
+                    "Downloaded {} bytes but file {} has {} bytes",
+                    written, file.id, expected
+                ))));
+            }
+        }
+        if let Some(checksum) = checksum {
+            let actual = format!("{:x}", hasher.finalize());
+            if actual != checksum {
+                return Err(self.error(TaskForceAIError::ChecksumMismatch {
+                    expected: checksum,
+                    actual,
+                }));
+            }
+        }
+
+        Ok(file)
+    }
+
+    pub(crate) async fn request_file_content(
+        &self,
+        file_id: &FileId,
+        offset: u64,
+    ) -> Result<reqwest::Response, TaskForceAIError> {
+        let segment = file_id.path_segment().map_err(|e| self.error(e))?;
+        let url = format!("{}/files/{}/content", self.base_url, segment);
+        let mut request = self.client.get(&url);
+        if offset > 0 {
+            request = request.header("Range", format!("bytes={}-", offset));
+        }
+        self.send(request).await
+    }
+
+    fn byte_stream(&self, response: reqwest::Response) -> ByteStream {
+        let metrics = self.metrics.clone();
+        Box::pin(response.bytes_stream().map(move |chunk| match chunk {
+            Ok(bytes) => {
+                metrics.record_bytes_downloaded(bytes.len() as u64);
+                Ok(bytes)
+            }
+            Err(e) => {
+                metrics.record_error("Network");
+                Err(TaskForceAIError::Network(e))
+            }
+        }))
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
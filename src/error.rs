@@ -8,8 +8,12 @@ pub enum TaskForceAIError {
     EmptyPrompt,
     #[error("Task ID must be a non-empty string")]
     EmptyTaskId,
+    #[error("Invalid ID: {0}")]
+    InvalidId(String),
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     #[error("Task failed: {0}")]
@@ -21,8 +25,43 @@ pub enum TaskForceAIError {
         status: reqwest::StatusCode,
         message: String,
     },
+    #[error("Invalid task options: {0}")]
+    InvalidOptions(String),
+    #[error("Invalid attachment: {0}")]
+    InvalidAttachment(String),
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
     #[error("Stream error: {0}")]
     Stream(String),
+    #[error("Local store error: {0}")]
+    Store(String),
     #[error("Other error: {0}")]
     Other(String),
 }
+
+impl TaskForceAIError {
+    /// The name of the error variant, suitable as a metrics label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TaskForceAIError::MissingApiKey => "MissingApiKey",
+            TaskForceAIError::EmptyPrompt => "EmptyPrompt",
+            TaskForceAIError::EmptyTaskId => "EmptyTaskId",
+            TaskForceAIError::InvalidId(_) => "InvalidId",
+            TaskForceAIError::Network(_) => "Network",
+            TaskForceAIError::Io(_) => "Io",
+            TaskForceAIError::Serialization(_) => "Serialization",
+            TaskForceAIError::TaskFailed(_) => "TaskFailed",
+            TaskForceAIError::Timeout => "Timeout",
+            TaskForceAIError::Api { .. } => "Api",
+            TaskForceAIError::InvalidOptions(_) => "InvalidOptions",
+            TaskForceAIError::InvalidAttachment(_) => "InvalidAttachment",
+            TaskForceAIError::ChecksumMismatch { .. } => "ChecksumMismatch",
+            TaskForceAIError::BudgetExceeded(_) => "BudgetExceeded",
+            TaskForceAIError::Stream(_) => "Stream",
+            TaskForceAIError::Store(_) => "Store",
+            TaskForceAIError::Other(_) => "Other",
+        }
+    }
+}
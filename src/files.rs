@@ -1,18 +1,28 @@
 use crate::client::TaskForceAI;
 use crate::error::TaskForceAIError;
+use crate::ids::FileId;
+use crate::{mime, telemetry};
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
 use reqwest::multipart::{Form, Part};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::watch;
+use tokio_util::io::ReaderStream;
 
 /// Represents an uploaded file.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct File {
-    pub id: String,
+    pub id: FileId,
     pub filename: String,
     pub purpose: String,
     pub bytes: i64,
-    #[serde(with = "chrono::serde::ts_seconds")]
+    #[serde(with = "crate::timestamps::lenient")]
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
@@ -23,6 +33,185 @@ pub struct File {
 pub struct FileUploadOptions {
     pub purpose: Option<String>,
     pub mime_type: Option<String>,
+    /// Receives progress updates while a streamed upload is sent
+    pub progress: Option<ProgressCallback>,
+}
+
+/// Field to sort a file listing by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSortField {
+    CreatedAt,
+    Filename,
+    Bytes,
+}
+
+impl FileSortField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FileSortField::CreatedAt => "created_at",
+            FileSortField::Filename => "filename",
+            FileSortField::Bytes => "bytes",
+        }
+    }
+}
+
+/// Direction of a sorted listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+/// Filters, sorting and paging for [`TaskForceAI::list_files_with_query`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListFilesQuery {
+    pub limit: Option<i32>,
+    pub offset: Option<i32>,
+    pub cursor: Option<String>,
+    pub purpose: Option<String>,
+    pub mime_type: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub filename_prefix: Option<String>,
+    pub sort_by: Option<FileSortField>,
+    pub order: Option<SortOrder>,
+}
+
+impl ListFilesQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(mut self, limit: i32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: i32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Continues from a cursor returned in [`FileListResponse::next_cursor`].
+    pub fn cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    pub fn purpose(mut self, purpose: impl Into<String>) -> Self {
+        self.purpose = Some(purpose.into());
+        self
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    pub fn created_after(mut self, created_after: DateTime<Utc>) -> Self {
+        self.created_after = Some(created_after);
+        self
+    }
+
+    pub fn created_before(mut self, created_before: DateTime<Utc>) -> Self {
+        self.created_before = Some(created_before);
+        self
+    }
+
+    pub fn filename_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.filename_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn sort_by(mut self, field: FileSortField, order: SortOrder) -> Self {
+        self.sort_by = Some(field);
+        self.order = Some(order);
+        self
+    }
+
+    /// Encodes the query as URL query parameters. Timestamps are sent as Unix seconds.
+    pub fn to_query_string(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(limit) = self.limit {
+            query.append_pair("limit", &limit.to_string());
+        }
+        if let Some(offset) = self.offset {
+            query.append_pair("offset", &offset.to_string());
+        }
+        if let Some(cursor) = &self.cursor {
+            query.append_pair("cursor", cursor);
+        }
+        if let Some(purpose) = &self.purpose {
+            query.append_pair("purpose", purpose);
+        }
+        if let Some(mime_type) = &self.mime_type {
+            query.append_pair("mime_type", mime_type);
+        }
+        if let Some(created_after) = self.created_after {
+            query.append_pair("created_after", &created_after.timestamp().to_string());
+        }
+        if let Some(created_before) = self.created_before {
+            query.append_pair("created_before", &created_before.timestamp().to_string());
+        }
+        if let Some(prefix) = &self.filename_prefix {
+            query.append_pair("filename_prefix", prefix);
+        }
+        if let Some(sort_by) = self.sort_by {
+            query.append_pair("sort", sort_by.as_str());
+        }
+        if let Some(order) = self.order {
+            query.append_pair("order", order.as_str());
+        }
+        query.finish()
+    }
+}
+
+/// Progress of a file transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransferProgress {
+    pub bytes_transferred: u64,
+    /// Total size, when known up front
+    pub total_bytes: Option<u64>,
+}
+
+/// A callback receiving [`TransferProgress`] updates.
+///
+/// Build one from a closure with [`ProgressCallback::new`], or from a
+/// `tokio::sync::watch::Sender` to observe progress from another task.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(TransferProgress) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(TransferProgress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, progress: TransferProgress) {
+        (self.0)(progress)
+    }
+}
+
+impl From<watch::Sender<TransferProgress>> for ProgressCallback {
+    fn from(sender: watch::Sender<TransferProgress>) -> Self {
+        Self::new(move |progress| {
+            let _ = sender.send(progress);
+        })
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
 }
 
 /// Response containing a list of files.
@@ -30,10 +219,25 @@ pub struct FileUploadOptions {
 pub struct FileListResponse {
     pub files: Vec<File>,
     pub total: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 impl TaskForceAI {
     /// Uploads a file to the API.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.upload_file",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                filename = %filename,
+                file_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn upload_file(
         &self,
         filename: &str,
@@ -44,90 +248,263 @@ impl TaskForceAI {
             .as_ref()
             .and_then(|o| o.mime_type.clone())
             .unwrap_or_else(|| "application/octet-stream".to_string());
+        let size = content.len() as u64;
 
-        let mut form = Form::new().part(
-            "file",
-            Part::bytes(content.to_vec())
-                .file_name(filename.to_string())
-                .mime_str(&mime_type)
-                .map_err(|e| TaskForceAIError::Other(e.to_string()))?,
-        );
-
-        if let Some(opts) = options {
-            if let Some(purpose) = opts.purpose {
-                form = form.text("purpose", purpose);
-            }
-            if let Some(mime_type) = opts.mime_type {
-                form = form.text("mime_type", mime_type);
-            }
+        let part = Part::bytes(content.to_vec())
+            .file_name(filename.to_string())
+            .mime_str(&mime_type)
+            .map_err(|e| self.error(TaskForceAIError::Other(e.to_string())))?;
+
+        let (purpose, mime_type) = options
+            .map(|o| (o.purpose, o.mime_type))
+            .unwrap_or_default();
+        let file = self.send_file_part(part, purpose, mime_type).await?;
+        self.metrics.record_bytes_uploaded(size);
+        Ok(file)
+    }
+
+    /// Uploads a file from disk, streaming it without loading it into memory.
+    ///
+    /// When no mime type is given, it is detected from the file's leading bytes
+    /// and then its extension. Files above the client's chunked upload threshold
+    /// are sent with [`upload_file_chunked`](Self::upload_file_chunked).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.upload_file_from_path",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                file_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn upload_file_from_path(
+        &self,
+        path: impl AsRef<Path>,
+        options: Option<FileUploadOptions>,
+    ) -> Result<File, TaskForceAIError> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| {
+                self.error(TaskForceAIError::Other(format!(
+                    "{} is not a file",
+                    path.display()
+                )))
+            })?;
+
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| self.error(e.into()))?;
+        let length = file
+            .metadata()
+            .await
+            .map_err(|e| self.error(e.into()))?
+            .len();
+
+        if length > self.chunked_upload_threshold {
+            return self.upload_file_chunked(path, options, None).await;
         }
 
-        let url = format!("{}/files", self.base_url);
-        let mut request = self.client.post(&url).multipart(form);
+        self.upload_file_from_reader(&filename, file, Some(length), options)
+            .await
+    }
 
-        if !self.api_key.is_empty() {
-            request = request.header("x-api-key", &self.api_key);
+    /// Uploads a file from any `AsyncRead`, streaming it without buffering it in memory.
+    ///
+    /// `length` is the total size in bytes, if known. When no mime type is given, it is
+    /// detected from the leading bytes and then the filename extension.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.upload_file_from_reader",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                filename = %filename,
+                file_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn upload_file_from_reader<R>(
+        &self,
+        filename: &str,
+        mut reader: R,
+        length: Option<u64>,
+        options: Option<FileUploadOptions>,
+    ) -> Result<File, TaskForceAIError>
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static,
+    {
+        let options = options.unwrap_or_default();
+
+        let mut head = Vec::with_capacity(mime::SNIFF_LEN);
+        (&mut reader)
+            .take(mime::SNIFF_LEN as u64)
+            .read_to_end(&mut head)
+            .await
+            .map_err(|e| self.error(e.into()))?;
+        let mime_type = options
+            .mime_type
+            .clone()
+            .unwrap_or_else(|| mime::detect(filename, &head));
+        let reader = std::io::Cursor::new(head).chain(reader);
+
+        let sent = Arc::new(AtomicU64::new(0));
+        let counter = sent.clone();
+        let progress = options.progress.clone();
+        let chunks = ReaderStream::new(reader).inspect_ok(move |chunk| {
+            let transferred =
+                counter.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+            if let Some(progress) = &progress {
+                progress.report(TransferProgress {
+                    bytes_transferred: transferred,
+                    total_bytes: length,
+                });
+            }
+        });
+
+        let body = reqwest::Body::wrap_stream(chunks);
+        let part = match length {
+            Some(length) => Part::stream_with_length(body, length),
+            None => Part::stream(body),
         }
-        request = request.header("X-SDK-Language", "rust");
+        .file_name(filename.to_string())
+        .mime_str(&mime_type)
+        .map_err(|e| self.error(TaskForceAIError::Other(e.to_string())))?;
 
-        let response = request.send().await?;
-        let status = response.status();
+        let file = self
+            .send_file_part(part, options.purpose, Some(mime_type))
+            .await?;
+        self.metrics
+            .record_bytes_uploaded(sent.load(Ordering::Relaxed));
+        Ok(file)
+    }
 
-        if !status.is_success() {
-            let message = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Failed to read error message".to_string());
-            return Err(TaskForceAIError::Api { status, message });
+    async fn send_file_part(
+        &self,
+        part: Part,
+        purpose: Option<String>,
+        mime_type: Option<String>,
+    ) -> Result<File, TaskForceAIError> {
+        let mut form = Form::new().part("file", part);
+        if let Some(purpose) = purpose {
+            form = form.text("purpose", purpose);
+        }
+        if let Some(mime_type) = mime_type {
+            form = form.text("mime_type", mime_type);
         }
 
-        Ok(response.json().await?)
+        let url = format!("{}/files", self.base_url);
+        let request = self.client.post(&url).multipart(form);
+
+        let response = self.send(request).await?;
+        let file: File = response.json().await.map_err(|e| self.error(e.into()))?;
+        telemetry::record("file_id", &file.id);
+        Ok(file)
     }
 
     /// Retrieves a list of uploaded files.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.list_files",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn list_files(
         &self,
         limit: i32,
         offset: i32,
     ) -> Result<FileListResponse, TaskForceAIError> {
-        let path = format!("/files?limit={}&offset={}", limit, offset);
+        self.list_files_with_query(&ListFilesQuery::new().limit(limit).offset(offset))
+            .await
+    }
+
+    /// Retrieves a filtered, sorted list of uploaded files.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.list_files_with_query",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn list_files_with_query(
+        &self,
+        query: &ListFilesQuery,
+    ) -> Result<FileListResponse, TaskForceAIError> {
+        let path = format!("/files?{}", query.to_query_string());
         self.request(reqwest::Method::GET, &path, None).await
     }
 
     /// Retrieves metadata for a specific file.
-    pub async fn get_file(&self, file_id: &str) -> Result<File, TaskForceAIError> {
-        let path = format!("/files/{}", file_id);
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.get_file",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                file_id = %file_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn get_file(&self, file_id: &FileId) -> Result<File, TaskForceAIError> {
+        let segment = file_id.path_segment().map_err(|e| self.error(e))?;
+        let path = format!("/files/{}", segment);
         self.request(reqwest::Method::GET, &path, None).await
     }
 
     /// Deletes a file by ID.
-    pub async fn delete_file(&self, file_id: &str) -> Result<(), TaskForceAIError> {
-        let path = format!("/files/{}", file_id);
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.delete_file",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                file_id = %file_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn delete_file(&self, file_id: &FileId) -> Result<(), TaskForceAIError> {
+        let segment = file_id.path_segment().map_err(|e| self.error(e))?;
+        let path = format!("/files/{}", segment);
         let _: serde_json::Value = self.request(reqwest::Method::DELETE, &path, None).await?;
         Ok(())
     }
 
     /// Downloads the content of a file.
-    pub async fn download_file(&self, file_id: &str) -> Result<Bytes, TaskForceAIError> {
-        let url = format!("{}/files/{}/content", self.base_url, file_id);
-        let mut request = self.client.get(&url);
-
-        if !self.api_key.is_empty() {
-            request = request.header("x-api-key", &self.api_key);
-        }
-        request = request.header("X-SDK-Language", "rust");
-
-        let response = request.send().await?;
-        let status = response.status();
-
-        if !status.is_success() {
-            let message = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Failed to read error message".to_string());
-            return Err(TaskForceAIError::Api { status, message });
-        }
-
-        Ok(response.bytes().await?)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.download_file",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                file_id = %file_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn download_file(&self, file_id: &FileId) -> Result<Bytes, TaskForceAIError> {
+        let response = self.request_file_content(file_id, 0).await?;
+        let bytes = response.bytes().await.map_err(|e| self.error(e.into()))?;
+        self.metrics.record_bytes_downloaded(bytes.len() as u64);
+        Ok(bytes)
     }
 }
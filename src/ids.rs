@@ -0,0 +1,240 @@
+//! Typed identifiers for tasks, files, threads and messages.
+//!
+//! Each kind of ID has its own type so one cannot be passed where another is expected.
+//! IDs are validated and percent-encoded when they are placed in a URL path.
+
+use crate::error::TaskForceAIError;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Characters escaped in a path segment: everything outside RFC 3986 `pchar`.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Percent-encodes `segment` for use as one URL path segment. `.` and `..` are rejected
+/// because URL parsing resolves them, even percent-encoded, as steps up the path.
+pub(crate) fn encode_path_segment(segment: &str, kind: &str) -> Result<String, TaskForceAIError> {
+    if segment == "." || segment == ".." {
+        return Err(TaskForceAIError::InvalidId(format!(
+            "{} cannot be {:?}",
+            kind, segment
+        )));
+    }
+    Ok(utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+}
+
+macro_rules! string_id {
+    ($(#[$doc:meta])* $name:ident, $kind:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            /// Creates an ID, rejecting blank values, `.`, `..` and control characters.
+            pub fn new(id: impl Into<String>) -> Result<Self, TaskForceAIError> {
+                let id = Self(id.into());
+                id.validate()?;
+                Ok(id)
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_inner(self) -> String {
+                self.0
+            }
+
+            /// Validates the ID and percent-encodes it for use as one URL path segment.
+            pub(crate) fn path_segment(&self) -> Result<String, TaskForceAIError> {
+                self.validate()?;
+                encode_path_segment(&self.0, $kind)
+            }
+
+            fn validate(&self) -> Result<(), TaskForceAIError> {
+                if self.0.trim().is_empty() {
+                    return Err(Self::empty_error());
+                }
+                if self.0 == "." || self.0 == ".." {
+                    return Err(TaskForceAIError::InvalidId(format!(
+                        "{} cannot be {:?}",
+                        $kind, self.0
+                    )));
+                }
+                if self.0.chars().any(char::is_control) {
+                    return Err(TaskForceAIError::InvalidId(format!(
+                        "{} contains control characters",
+                        $kind
+                    )));
+                }
+                Ok(())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = TaskForceAIError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::new(s)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+macro_rules! numeric_id {
+    ($(#[$doc:meta])* $name:ident, $kind:literal) => {
+        $(#[$doc])*
+        #[derive(
+            Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub i64);
+
+        impl $name {
+            pub fn get(self) -> i64 {
+                self.0
+            }
+
+            /// Validates the ID for use as a URL path segment.
+            pub(crate) fn path_segment(self) -> Result<String, TaskForceAIError> {
+                if self.0 <= 0 {
+                    return Err(TaskForceAIError::InvalidId(format!(
+                        "{} must be positive, got {}",
+                        $kind, self.0
+                    )));
+                }
+                Ok(self.0.to_string())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = TaskForceAIError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.trim().parse().map(Self).map_err(|_| {
+                    TaskForceAIError::InvalidId(format!("{} must be an integer, got {:?}", $kind, s))
+                })
+            }
+        }
+
+        impl From<i64> for $name {
+            fn from(id: i64) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl From<$name> for serde_json::Value {
+            fn from(id: $name) -> Self {
+                id.0.into()
+            }
+        }
+
+        impl PartialEq<i64> for $name {
+            fn eq(&self, other: &i64) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+string_id!(
+    /// Identifies a submitted task.
+    TaskId,
+    "Task ID"
+);
+
+impl TaskId {
+    fn empty_error() -> TaskForceAIError {
+        TaskForceAIError::EmptyTaskId
+    }
+}
+
+string_id!(
+    /// Identifies an uploaded file.
+    FileId,
+    "File ID"
+);
+
+impl FileId {
+    fn empty_error() -> TaskForceAIError {
+        TaskForceAIError::InvalidId("File ID must be a non-empty string".to_string())
+    }
+}
+
+numeric_id!(
+    /// Identifies a conversation thread.
+    ThreadId,
+    "Thread ID"
+);
+
+numeric_id!(
+    /// Identifies a message within a thread.
+    MessageId,
+    "Message ID"
+);
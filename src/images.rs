@@ -0,0 +1,231 @@
+use crate::error::TaskForceAIError;
+use crate::mime;
+use crate::types::ImageAttachment;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::path::Path;
+
+/// Largest decoded image the API accepts, in bytes.
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Image MIME types the API accepts.
+pub const SUPPORTED_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Vision detail levels the API accepts.
+pub const IMAGE_DETAIL_LEVELS: &[&str] = &["auto", "low", "high"];
+
+fn invalid(message: String) -> TaskForceAIError {
+    TaskForceAIError::InvalidAttachment(message)
+}
+
+impl ImageAttachment {
+    /// Builds an attachment from raw image bytes, detecting the format from its magic bytes.
+    pub fn from_bytes(data: &[u8], name: Option<String>) -> Result<Self, TaskForceAIError> {
+        let mime_type = mime::sniff(data)
+            .filter(|mime_type| SUPPORTED_IMAGE_TYPES.contains(mime_type))
+            .ok_or_else(|| {
+                invalid(format!(
+                    "{} is not a supported image; expected one of {:?}",
+                    name.as_deref().unwrap_or("attachment"),
+                    SUPPORTED_IMAGE_TYPES
+                ))
+            })?;
+
+        let attachment = Self {
+            data: STANDARD.encode(data),
+            mime_type: mime_type.to_string(),
+            name,
+            detail: None,
+        };
+        attachment.validate()?;
+        Ok(attachment)
+    }
+
+    /// Reads an image from disk.
+    pub async fn from_path(path: impl AsRef<Path>) -> Result<Self, TaskForceAIError> {
+        let path = path.as_ref();
+        let data = tokio::fs::read(path).await?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        Self::from_bytes(&data, name)
+    }
+
+    /// Downloads an image from a URL. No SDK credentials are sent with the request.
+    ///
+    /// The download stops as soon as the image is known to exceed [`MAX_IMAGE_BYTES`].
+    pub async fn from_url_fetch(url: &str) -> Result<Self, TaskForceAIError> {
+        let mut response = reqwest::get(url).await?.error_for_status()?;
+        let name = response
+            .url()
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string);
+        let too_large = || {
+            invalid(format!(
+                "{} is larger than the limit of {} bytes",
+                name.as_deref().unwrap_or("attachment"),
+                MAX_IMAGE_BYTES
+            ))
+        };
+        if response
+            .content_length()
+            .is_some_and(|len| len > MAX_IMAGE_BYTES as u64)
+        {
+            return Err(too_large());
+        }
+        let mut data = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if data.len() + chunk.len() > MAX_IMAGE_BYTES {
+                return Err(too_large());
+            }
+            data.extend_from_slice(&chunk);
+        }
+        Self::from_bytes(&data, name)
+    }
+
+    /// Sets the vision detail level: "auto", "low" or "high".
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Checks the attachment against the limits the API enforces.
+    pub fn validate(&self) -> Result<(), TaskForceAIError> {
+        let name = self.name.as_deref().unwrap_or("attachment");
+        if !SUPPORTED_IMAGE_TYPES.contains(&self.mime_type.as_str()) {
+            return Err(invalid(format!(
+                "{} has unsupported mime type \"{}\"; expected one of {:?}",
+                name, self.mime_type, SUPPORTED_IMAGE_TYPES
+            )));
+        }
+        if let Some(detail) = &self.detail {
+            if !IMAGE_DETAIL_LEVELS.contains(&detail.as_str()) {
+                return Err(invalid(format!(
+                    "{} has unsupported detail \"{}\"; expected one of {:?}",
+                    name, detail, IMAGE_DETAIL_LEVELS
+                )));
+            }
+        }
+
+        let decoded = STANDARD
+            .decode(&self.data)
+            .map_err(|e| invalid(format!("{} is not valid base64: {}", name, e)))?;
+        if decoded.len() > MAX_IMAGE_BYTES {
+            return Err(invalid(format!(
+                "{} is {} bytes; the limit is {} bytes",
+                name,
+                decoded.len(),
+                MAX_IMAGE_BYTES
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Limits an image is scaled and recompressed to fit before it is encoded.
+#[cfg(feature = "image")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageBudget {
+    /// Maximum width times height
+    pub max_pixels: Option<u64>,
+    /// Maximum encoded size in bytes
+    pub max_bytes: Option<usize>,
+}
+
+#[cfg(feature = "image")]
+impl Default for ImageBudget {
+    fn default() -> Self {
+        Self {
+            max_pixels: Some(2048 * 2048),
+            max_bytes: Some(MAX_IMAGE_BYTES),
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl ImageAttachment {
+    /// Like [`from_bytes`](Self::from_bytes), but downscales or recompresses the image to fit `budget`.
+    /// Images already within the budget are attached unchanged.
+    pub fn from_bytes_with_budget(
+        data: &[u8],
+        name: Option<String>,
+        budget: &ImageBudget,
+    ) -> Result<Self, TaskForceAIError> {
+        let fits_bytes = budget.max_bytes.is_none_or(|max| data.len() <= max);
+        let image = image::load_from_memory(data)
+            .map_err(|e| invalid(format!("failed to decode image: {}", e)))?;
+        let pixels = u64::from(image.width()) * u64::from(image.height());
+        let fits_pixels = budget.max_pixels.is_none_or(|max| pixels <= max);
+        if fits_bytes && fits_pixels {
+            return Self::from_bytes(data, name);
+        }
+
+        let mut image = match budget.max_pixels {
+            Some(max) if pixels > max => {
+                let scale = (max as f64 / pixels as f64).sqrt();
+                let width = ((f64::from(image.width()) * scale) as u32).max(1);
+                let height = ((f64::from(image.height()) * scale) as u32).max(1);
+                image.resize(width, height, image::imageops::FilterType::Lanczos3)
+            }
+            _ => image,
+        };
+
+        let keep_png = mime::sniff(data) == Some("image/png");
+        let mut quality = 85;
+        loop {
+            let encoded = encode(&image, keep_png, quality)?;
+            if budget.max_bytes.is_none_or(|max| encoded.len() <= max) {
+                return Self::from_bytes(&encoded, name);
+            }
+            if !keep_png && quality > 50 {
+                quality -= 10;
+            } else if image.width() > 1 && image.height() > 1 {
+                image = image.resize(
+                    image.width() * 3 / 4,
+                    image.height() * 3 / 4,
+                    image::imageops::FilterType::Lanczos3,
+                );
+            } else {
+                return Err(invalid(
+                    "image cannot be compressed within the byte budget".to_string(),
+                ));
+            }
+        }
+    }
+
+    /// Like [`from_path`](Self::from_path), but downscales or recompresses the image to fit `budget`.
+    pub async fn from_path_with_budget(
+        path: impl AsRef<Path>,
+        budget: &ImageBudget,
+    ) -> Result<Self, TaskForceAIError> {
+        let path = path.as_ref();
+        let data = tokio::fs::read(path).await?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        Self::from_bytes_with_budget(&data, name, budget)
+    }
+}
+
+#[cfg(feature = "image")]
+fn encode(
+    image: &image::DynamicImage,
+    png: bool,
+    quality: u8,
+) -> Result<Vec<u8>, TaskForceAIError> {
+    let mut encoded = Vec::new();
+    let result = if png {
+        image.write_with_encoder(image::codecs::png::PngEncoder::new(&mut encoded))
+    } else {
+        image
+            .to_rgb8()
+            .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
+                &mut encoded,
+                quality,
+            ))
+    };
+    result.map_err(|e| invalid(format!("failed to encode image: {}", e)))?;
+    Ok(encoded)
+}
@@ -1,20 +1,68 @@
+pub mod attachments;
+pub mod branches;
+pub mod budget;
 pub mod client;
+pub mod conversation;
+pub mod downloads;
 pub mod error;
 pub mod files;
+pub mod ids;
+pub mod images;
+pub mod messages;
+pub mod metrics;
+pub mod middleware;
+mod mime;
+pub mod models;
+pub mod pagination;
+pub mod search;
+#[cfg(feature = "local-store")]
+pub mod store;
 pub mod stream;
+pub mod sync;
+mod telemetry;
 pub mod threads;
+pub mod timestamps;
+pub mod transcripts;
 pub mod types;
+pub mod uploads;
+pub mod usage;
 
+pub use branches::{ForkThreadOptions, ThreadBranch};
+pub use budget::{Budget, BudgetStatus};
 pub use client::TaskForceAI;
+pub use conversation::{
+    ContextStrategy, Conversation, ConversationStream, KeepAll, PinSystemMessages, SummarizeOlder,
+    TrimToTokens,
+};
+pub use downloads::{ByteStream, DownloadOptions};
 pub use error::TaskForceAIError;
-pub use files::{File, FileListResponse, FileUploadOptions};
+pub use files::{
+    File, FileListResponse, FileSortField, FileUploadOptions, ListFilesQuery, ProgressCallback,
+    SortOrder, TransferProgress,
+};
+pub use ids::{FileId, MessageId, TaskId, ThreadId};
+#[cfg(feature = "image")]
+pub use images::ImageBudget;
+pub use messages::{ContentPart, MessageContent, Role, ToolCall, ToolResult};
+pub use metrics::MetricsRecorder;
+pub use middleware::{Middleware, RequestContext};
+pub use models::{Model, ModelPricing, ModelStatus};
+pub use pagination::{Page, PageRequest, Paginator};
+pub use search::{SearchHit, SearchIndex};
+#[cfg(feature = "local-store")]
+pub use store::{LocalStore, LocalSyncReport};
+pub use sync::{SyncManifest, SyncOptions, SyncReport};
 pub use threads::{
     CreateThreadOptions, Thread, ThreadListResponse, ThreadMessage, ThreadMessagesResponse,
-    ThreadRunOptions, ThreadRunResponse,
+    ThreadRunOptions, ThreadRunResponse, ThreadRunResult, UpdateThreadOptions,
 };
+pub use transcripts::{ExportFormat, ThreadTranscript};
 pub use types::{
-    ImageAttachment, TaskForceAIOptions, TaskStatus, TaskStatusValue, TaskSubmissionOptions,
+    ImageAttachment, ReasoningEffort, ResponseFormat, TaskForceAIOptions, TaskPriority, TaskStatus,
+    TaskStatusValue, TaskSubmissionOptions,
 };
+pub use uploads::{ChunkedUploadOptions, ResumeToken, UploadedPart};
+pub use usage::{AgentUsage, Usage};
 
 #[cfg(test)]
 mod tests {
@@ -22,6 +70,7 @@ mod tests {
     use crate::client::{DEFAULT_BASE_URL, DEFAULT_TIMEOUT_SECS};
     use futures_util::StreamExt;
     use mockito::{Matcher, Server};
+    use std::collections::HashMap;
     use std::time::Duration;
 
     #[tokio::test]
@@ -59,7 +108,10 @@ mod tests {
         assert_eq!(status.status, TaskStatusValue::Completed);
 
         // Test stream_task_status
-        let mut stream = client.stream_task_status("mock-id").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("mock-id"))
+            .await
+            .unwrap();
         let ev = stream.next().await.unwrap().unwrap();
         assert_eq!(ev.status, TaskStatusValue::Completed);
     }
@@ -119,7 +171,11 @@ mod tests {
         .unwrap();
 
         let res = client
-            .wait_for_completion("task-1", Some(Duration::from_millis(1)), Some(2))
+            .wait_for_completion(
+                &TaskId::from("task-1"),
+                Some(Duration::from_millis(1)),
+                Some(2),
+            )
             .await;
         assert!(matches!(res, Err(TaskForceAIError::Timeout)));
     }
@@ -141,7 +197,9 @@ mod tests {
         })
         .unwrap();
 
-        let res = client.wait_for_completion("task-1", None, None).await;
+        let res = client
+            .wait_for_completion(&TaskId::from("task-1"), None, None)
+            .await;
         match res {
             Err(TaskForceAIError::TaskFailed(msg)) => assert_eq!(msg, "oops"),
             _ => panic!("Expected TaskFailed error"),
@@ -165,7 +223,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
 
         let ev1 = stream.next().await.unwrap().unwrap();
         assert_eq!(ev1.status, TaskStatusValue::Processing);
@@ -221,7 +282,7 @@ mod tests {
         })
         .unwrap();
 
-        let res = client.stream_task_status("task-1").await;
+        let res = client.stream_task_status(&TaskId::from("task-1")).await;
         assert!(matches!(res, Err(TaskForceAIError::Api { .. })));
     }
 
@@ -242,7 +303,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         let res = stream.next().await.unwrap();
         assert!(matches!(res, Err(TaskForceAIError::Serialization(_))));
     }
@@ -255,7 +319,7 @@ mod tests {
         })
         .unwrap();
 
-        let res = client.get_task_status("  ").await;
+        let res = client.get_task_status(&TaskId::from("  ")).await;
         assert!(matches!(res, Err(TaskForceAIError::EmptyTaskId)));
     }
 
@@ -267,7 +331,7 @@ mod tests {
         })
         .unwrap();
 
-        let res = client.stream_task_status("").await;
+        let res = client.stream_task_status(&TaskId::from("")).await;
         assert!(matches!(res, Err(TaskForceAIError::EmptyTaskId)));
     }
 
@@ -288,7 +352,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         let ev = stream.next().await.unwrap().unwrap();
         assert_eq!(ev.status, TaskStatusValue::Completed);
     }
@@ -319,6 +386,172 @@ mod tests {
         assert_eq!(task_id, "task-opts");
     }
 
+    #[tokio::test]
+    async fn test_submit_task_typed_options() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/run")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "options": {
+                    "systemPrompt": "Be terse.",
+                    "temperature": 0.5,
+                    "maxOutputTokens": 512,
+                    "agentCount": 2,
+                    "agentRoles": ["researcher", "critic"],
+                    "reasoningEffort": "high",
+                    "responseFormat": {"type": "json_schema", "name": "answer", "schema": {"type": "object"}},
+                    "tags": ["billing"],
+                    "metadata": {"customer": 42},
+                    "priority": "low",
+                    "experimental": true,
+                }
+            })))
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-typed"}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = TaskSubmissionOptions {
+            system_prompt: Some("Be terse.".to_string()),
+            temperature: Some(0.5),
+            max_output_tokens: Some(512),
+            agent_count: Some(2),
+            agent_roles: Some(vec!["researcher".to_string(), "critic".to_string()]),
+            reasoning_effort: Some(ReasoningEffort::High),
+            response_format: Some(ResponseFormat::JsonSchema {
+                name: "answer".to_string(),
+                schema: serde_json::json!({"type": "object"}),
+            }),
+            tags: Some(vec!["billing".to_string()]),
+            metadata: Some(HashMap::from([("customer".to_string(), 42.into())])),
+            priority: Some(TaskPriority::Low),
+            additional_options: HashMap::from([("experimental".to_string(), true.into())]),
+            ..Default::default()
+        };
+        let task_id = client.submit_task("hello", Some(opts)).await.unwrap();
+        assert_eq!(task_id, "task-typed");
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_additional_options_may_carry_unset_typed_fields() {
+        let passthrough = TaskSubmissionOptions {
+            additional_options: HashMap::from([
+                ("temperature".to_string(), 0.5.into()),
+                ("systemPrompt".to_string(), "Be terse.".into()),
+            ]),
+            ..Default::default()
+        };
+        passthrough.validate().unwrap();
+
+        let populated = TaskSubmissionOptions {
+            model_id: Some("gpt".to_string()),
+            system_prompt: Some("Be terse.".to_string()),
+            temperature: Some(0.5),
+            max_output_tokens: Some(512),
+            agent_count: Some(2),
+            agent_roles: Some(vec!["researcher".to_string()]),
+            reasoning_effort: Some(ReasoningEffort::High),
+            response_format: Some(ResponseFormat::JsonObject),
+            tags: Some(vec!["billing".to_string()]),
+            metadata: Some(HashMap::new()),
+            priority: Some(TaskPriority::Low),
+            silent: Some(true),
+            mock: Some(false),
+            vercel_ai_key: Some("key".to_string()),
+            file_ids: Some(vec![FileId::from("file-1")]),
+            ..Default::default()
+        };
+        populated.validate().unwrap();
+        let json = serde_json::to_value(&populated).unwrap();
+        let mut serialized: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut typed = crate::types::TYPED_OPTION_KEYS.to_vec();
+        serialized.sort_unstable();
+        typed.sort_unstable();
+        assert_eq!(serialized, typed);
+        for key in typed {
+            let conflicting = TaskSubmissionOptions {
+                additional_options: HashMap::from([(key.to_string(), json[key].clone())]),
+                ..populated.clone()
+            };
+            let err = conflicting.validate().unwrap_err();
+            assert!(err.to_string().contains("conflicts"), "{}: {}", key, err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_task_rejects_invalid_options() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/run").expect(0).create_async().await;
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let invalid = [
+            TaskSubmissionOptions {
+                temperature: Some(2.5),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                max_output_tokens: Some(0),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                agent_count: Some(0),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                agent_count: Some(1),
+                agent_roles: Some(vec!["a".to_string(), "b".to_string()]),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                response_format: Some(ResponseFormat::JsonSchema {
+                    name: "answer".to_string(),
+                    schema: serde_json::json!("object"),
+                }),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                tags: Some(vec![" ".to_string()]),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                temperature: Some(1.0),
+                additional_options: HashMap::from([("temperature".to_string(), 0.2.into())]),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                additional_options: HashMap::from([("maxOutputTokens".to_string(), 0.into())]),
+                ..Default::default()
+            },
+            TaskSubmissionOptions {
+                additional_options: HashMap::from([("temperature".to_string(), "hot".into())]),
+                ..Default::default()
+            },
+        ];
+        for opts in invalid {
+            let err = client.submit_task("hello", Some(opts)).await.unwrap_err();
+            assert_eq!(err.kind(), "InvalidOptions", "{}", err);
+        }
+        mock.assert_async().await;
+    }
+
     #[tokio::test]
     async fn test_stream_empty_end_unique() {
         let mut server = Server::new_async().await;
@@ -336,7 +569,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         let ev = stream.next().await.unwrap().unwrap();
         assert_eq!(ev.status, TaskStatusValue::Processing);
         assert!(stream.next().await.is_none());
@@ -359,7 +595,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         let _ = stream.next().await;
         assert!(stream.next().await.is_none());
     }
@@ -381,7 +620,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         let ev = stream.next().await.unwrap().unwrap();
         assert_eq!(ev.status, TaskStatusValue::Completed);
         assert!(stream.next().await.is_none());
@@ -404,7 +646,9 @@ mod tests {
         })
         .unwrap();
 
-        let res = client.wait_for_completion("task-1", None, None).await;
+        let res = client
+            .wait_for_completion(&TaskId::from("task-1"), None, None)
+            .await;
         match res {
             Err(TaskForceAIError::TaskFailed(msg)) => assert_eq!(msg, "Unknown error"),
             _ => panic!("Expected TaskFailed error"),
@@ -448,7 +692,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         let res = stream.next().await.unwrap();
         assert!(matches!(res, Err(TaskForceAIError::Serialization(_))));
     }
@@ -470,7 +717,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         assert!(stream.next().await.is_none());
     }
 
@@ -490,6 +740,9 @@ mod tests {
         let e = TaskForceAIError::Stream("oops".to_string());
         assert_eq!(e.to_string(), "Stream error: oops");
 
+        let e = TaskForceAIError::Store("oops".to_string());
+        assert_eq!(e.to_string(), "Local store error: oops");
+
         let e = TaskForceAIError::Other("oops".to_string());
         assert_eq!(e.to_string(), "Other error: oops");
     }
@@ -530,7 +783,9 @@ mod tests {
         })
         .unwrap();
 
-        let res = client.wait_for_completion("task-1", None, None).await;
+        let res = client
+            .wait_for_completion(&TaskId::from("task-1"), None, None)
+            .await;
         assert!(matches!(res, Err(TaskForceAIError::Api { status, .. }) if status == 500));
     }
 
@@ -551,7 +806,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         assert!(stream.next().await.is_none());
     }
 
@@ -572,7 +830,10 @@ mod tests {
         })
         .unwrap();
 
-        let mut stream = client.stream_task_status("task-1").await.unwrap();
+        let mut stream = client
+            .stream_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
         let _ = stream.next().await;
 
         drop(server);
@@ -611,6 +872,7 @@ mod tests {
         let opts = FileUploadOptions {
             purpose: Some("test".to_string()),
             mime_type: Some("text/plain".to_string()),
+            ..Default::default()
         };
         let file = client
             .upload_file("test.txt", "content".as_bytes().to_vec().into(), Some(opts))
@@ -659,7 +921,7 @@ mod tests {
         })
         .unwrap();
 
-        let file = client.get_file("file-1").await.unwrap();
+        let file = client.get_file(&FileId::from("file-1")).await.unwrap();
         assert_eq!(file.id, "file-1");
     }
 
@@ -680,7 +942,7 @@ mod tests {
         })
         .unwrap();
 
-        client.delete_file("file-1").await.unwrap();
+        client.delete_file(&FileId::from("file-1")).await.unwrap();
     }
 
     #[tokio::test]
@@ -701,7 +963,7 @@ mod tests {
         })
         .unwrap();
 
-        let bytes = client.download_file("file-1").await.unwrap();
+        let bytes = client.download_file(&FileId::from("file-1")).await.unwrap();
         assert_eq!(bytes, "file content".as_bytes());
     }
 
@@ -773,10 +1035,76 @@ mod tests {
         })
         .unwrap();
 
-        let thread = client.get_thread(1).await.unwrap();
+        let thread = client.get_thread(ThreadId(1)).await.unwrap();
         assert_eq!(thread.id, 1);
     }
 
+    #[test]
+    fn test_lenient_timestamps() {
+        let expected = chrono::DateTime::from_timestamp(1672531200, 0).unwrap();
+        for created_at in [
+            "1672531200",
+            "1672531200000",
+            "1672531200.0",
+            "\"1672531200\"",
+            "\"2023-01-01T00:00:00Z\"",
+            "\"2023-01-01T01:00:00+01:00\"",
+        ] {
+            let thread: Thread = serde_json::from_str(&format!(
+                r#"{{"id": 1, "title": "t", "created_at": {0}, "updated_at": {0}}}"#,
+                created_at
+            ))
+            .unwrap();
+            assert_eq!(thread.created_at, expected, "{}", created_at);
+            assert_eq!(thread.updated_at, expected, "{}", created_at);
+        }
+
+        let file: File = serde_json::from_str(
+            r#"{"id": "f1", "filename": "a.txt", "purpose": "p", "bytes": 1, "created_at": 1672531200123}"#,
+        )
+        .unwrap();
+        assert_eq!(file.created_at.timestamp_millis(), 1672531200123);
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(json["created_at"], "2023-01-01T00:00:00.123Z");
+        let again: File = serde_json::from_value(json).unwrap();
+        assert_eq!(again.created_at, file.created_at);
+
+        let message = serde_json::to_value(message(1, Role::User, "hi")).unwrap();
+        let message: ThreadMessage = serde_json::from_value(message).unwrap();
+        assert_eq!(message.content.text(), "hi");
+        assert!(serde_json::from_str::<Thread>(
+            r#"{"id": 1, "title": "t", "created_at": "yesterday", "updated_at": 0}"#
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_feature_conversions() {
+        let thread: Thread = serde_json::from_str(
+            r#"{"id": 1, "title": "t", "created_at": 1672531200500, "updated_at": "2023-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(thread.created_at_time().unix_timestamp(), 1672531200);
+        assert_eq!(thread.created_at_time().millisecond(), 500);
+        assert_eq!(
+            crate::timestamps::from_time(thread.updated_at_time()),
+            thread.updated_at
+        );
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Event {
+            #[serde(with = "crate::timestamps::time_lenient")]
+            at: time::OffsetDateTime,
+        }
+        let event: Event = serde_json::from_str(r#"{"at": "2023-01-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(event.at.unix_timestamp(), 1672531200);
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"at":1672531200}"#
+        );
+    }
+
     #[tokio::test]
     async fn test_delete_thread() {
         let mut server = Server::new_async().await;
@@ -794,7 +1122,7 @@ mod tests {
         })
         .unwrap();
 
-        client.delete_thread(1).await.unwrap();
+        client.delete_thread(ThreadId(1)).await.unwrap();
     }
 
     #[tokio::test]
@@ -814,7 +1142,10 @@ mod tests {
         })
         .unwrap();
 
-        let res = client.get_thread_messages(1, 10, 0).await.unwrap();
+        let res = client
+            .get_thread_messages(ThreadId(1), 10, 0)
+            .await
+            .unwrap();
         assert_eq!(res.messages.len(), 1);
     }
 
@@ -839,7 +1170,3292 @@ mod tests {
             prompt: "run".to_string(),
             ..Default::default()
         };
-        let res = client.run_in_thread(1, opts).await.unwrap();
+        let res = client.run_in_thread(ThreadId(1), opts).await.unwrap();
         assert_eq!(res.task_id, "task-t1");
     }
+
+    // --- Middleware Tests ---
+
+    struct RecordingMiddleware {
+        name: &'static str,
+        events: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for RecordingMiddleware {
+        async fn before_request(
+            &self,
+            request: &mut reqwest::Request,
+        ) -> Result<(), TaskForceAIError> {
+            request
+                .headers_mut()
+                .insert("x-trace", reqwest::header::HeaderValue::from_static("abc"));
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{}:before", self.name));
+            Ok(())
+        }
+
+        async fn after_response(
+            &self,
+            context: &RequestContext,
+            response: &reqwest::Response,
+        ) -> Result<(), TaskForceAIError> {
+            self.events.lock().unwrap().push(format!(
+                "{}:after:{}:{}",
+                self.name,
+                context.url.path(),
+                response.status().as_u16()
+            ));
+            Ok(())
+        }
+
+        async fn on_error(&self, _context: &RequestContext, error: &TaskForceAIError) {
+            let kind = match error {
+                TaskForceAIError::Api { status, .. } => status.as_u16().to_string(),
+                _ => "other".to_string(),
+            };
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{}:error:{}", self.name, kind));
+        }
+    }
+
+    struct GatewayMiddleware {
+        base_url: String,
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for GatewayMiddleware {
+        async fn before_request(
+            &self,
+            request: &mut reqwest::Request,
+        ) -> Result<(), TaskForceAIError> {
+            let rewritten = format!("{}/gateway{}", self.base_url, request.url().path());
+            *request.url_mut() = rewritten.parse().unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_chain_order() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/files/file-1/content")
+            .match_header("x-trace", "abc")
+            .with_status(200)
+            .with_body("file content")
+            .create_async()
+            .await;
+
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_middleware(RecordingMiddleware {
+            name: "outer",
+            events: events.clone(),
+        })
+        .with_middleware(RecordingMiddleware {
+            name: "inner",
+            events: events.clone(),
+        });
+
+        client.download_file(&FileId::from("file-1")).await.unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "outer:before",
+                "inner:before",
+                "inner:after:/files/file-1/content:200",
+                "outer:after:/files/file-1/content:200",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_on_error() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/stream/task-1")
+            .with_status(403)
+            .create_async()
+            .await;
+
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_middleware(RecordingMiddleware {
+            name: "m",
+            events: events.clone(),
+        });
+
+        let res = client.stream_task_status(&TaskId::from("task-1")).await;
+        assert!(matches!(res, Err(TaskForceAIError::Api { .. })));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["m:before", "m:after:/stream/task-1:403", "m:error:403"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rewrites_url() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/gateway/files")
+            .with_status(200)
+            .with_body(r#"{"id": "file-123", "filename": "test.txt", "purpose": "test", "bytes": 7, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_middleware(GatewayMiddleware {
+            base_url: server.url(),
+        });
+
+        let file = client
+            .upload_file("test.txt", "content".as_bytes().to_vec().into(), None)
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-123");
+    }
+
+    // --- Tracing Tests ---
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_tracing_propagates_traceparent() {
+        use opentelemetry::trace::TracerProvider;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/status/task-1")
+            .match_header(
+                "traceparent",
+                Matcher::Regex("^00-[0-9a-f]{32}-[0-9a-f]{16}-01$".to_string()),
+            )
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1", "status": "completed"}"#)
+            .create_async()
+            .await;
+
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let status = client
+            .get_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
+        assert_eq!(status.status, TaskStatusValue::Completed);
+    }
+
+    // --- Metrics Tests ---
+
+    #[derive(Default, Clone)]
+    struct RecordingMetrics {
+        events: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl MetricsRecorder for RecordingMetrics {
+        fn record_request(
+            &self,
+            method: &reqwest::Method,
+            endpoint: &str,
+            status: Option<u16>,
+            _latency: Duration,
+        ) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("request:{} {}:{:?}", method, endpoint, status));
+        }
+
+        fn record_task_duration(&self, _duration: Duration) {
+            self.events
+                .lock()
+                .unwrap()
+                .push("task_duration".to_string());
+        }
+
+        fn record_poll_attempts(&self, attempts: u32) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("poll_attempts:{}", attempts));
+        }
+
+        fn record_bytes_uploaded(&self, bytes: u64) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("uploaded:{}", bytes));
+        }
+
+        fn record_bytes_downloaded(&self, bytes: u64) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("downloaded:{}", bytes));
+        }
+
+        fn record_error(&self, kind: &'static str) {
+            self.events.lock().unwrap().push(format!("error:{}", kind));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_run_task() {
+        let mut server = Server::new_async().await;
+        let _run_mock = server
+            .mock("POST", "/run")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1"}"#)
+            .create_async()
+            .await;
+        let _status_mock = server
+            .mock("GET", "/status/task-1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1", "status": "completed"}"#)
+            .create_async()
+            .await;
+
+        let metrics = RecordingMetrics::default();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_metrics(metrics.clone());
+
+        client.run_task("hi", None, None, None).await.unwrap();
+        assert_eq!(
+            *metrics.events.lock().unwrap(),
+            vec![
+                "request:POST /run:Some(200)",
+                "request:GET /status/{id}:Some(200)",
+                "poll_attempts:1",
+                "task_duration",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_errors_and_bytes() {
+        let mut server = Server::new_async().await;
+        let _download_mock = server
+            .mock("GET", "/files/file-1/content")
+            .with_status(200)
+            .with_body("file content")
+            .create_async()
+            .await;
+        let _delete_mock = server
+            .mock("DELETE", "/files/file-2")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let metrics = RecordingMetrics::default();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_metrics(metrics.clone());
+
+        client.download_file(&FileId::from("file-1")).await.unwrap();
+        assert!(client.delete_file(&FileId::from("file-2")).await.is_err());
+        assert!(client.submit_task("", None).await.is_err());
+        assert_eq!(
+            *metrics.events.lock().unwrap(),
+            vec![
+                "request:GET /files/{id}/content:Some(200)",
+                "downloaded:12",
+                "request:DELETE /files/{id}:Some(404)",
+                "error:Api",
+                "error:EmptyPrompt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_endpoint_label() {
+        use crate::metrics::endpoint_label;
+        assert_eq!(endpoint_label("/run"), "/run");
+        assert_eq!(
+            endpoint_label("/threads/42/messages"),
+            "/threads/{id}/messages"
+        );
+        assert_eq!(endpoint_label("/threads/42/runs"), "/threads/{id}/runs");
+        assert_eq!(endpoint_label(""), "/");
+    }
+
+    // --- Usage Tests ---
+
+    #[test]
+    fn test_usage_from_metadata() {
+        let status: TaskStatus = serde_json::from_str(
+            r#"{"taskId": "t", "status": "completed", "metadata": {"usage": {"agents": [
+                {"agent": "researcher", "modelId": "m1", "inputTokens": 10, "outputTokens": 5, "cost": 0.5},
+                {"agent": "writer", "modelId": "m2", "inputTokens": 20, "outputTokens": 15, "cost": 1.0}
+            ]}}}"#,
+        )
+        .unwrap();
+
+        let usage = status.usage().unwrap();
+        assert_eq!(usage.input_tokens, 30);
+        assert_eq!(usage.output_tokens, 20);
+        assert_eq!(usage.total_tokens(), 50);
+        assert_eq!(usage.cost, 1.5);
+        assert_eq!(usage.models, vec!["m1", "m2"]);
+
+        let status: TaskStatus =
+            serde_json::from_str(r#"{"taskId": "t", "status": "completed"}"#).unwrap();
+        assert!(status.usage().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_usage_accumulates_across_tasks() {
+        let mut server = Server::new_async().await;
+        let _mock1 = server
+            .mock("GET", "/status/task-1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1", "status": "completed", "metadata": {"usage": {"inputTokens": 100, "outputTokens": 50, "cost": 0.25, "models": ["m1"]}}}"#)
+            .create_async()
+            .await;
+        let _mock2 = server
+            .mock("GET", "/status/task-2")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-2", "status": "completed", "metadata": {"usage": {"input_tokens": 10, "output_tokens": 5, "cost": 0.05, "models": ["m2"]}}}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        client
+            .wait_for_completion(&TaskId::from("task-1"), None, None)
+            .await
+            .unwrap();
+        client
+            .get_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
+        client
+            .wait_for_completion(&TaskId::from("task-2"), None, None)
+            .await
+            .unwrap();
+
+        let usage = client.usage();
+        assert_eq!(usage.input_tokens, 110);
+        assert_eq!(usage.output_tokens, 55);
+        assert!((usage.cost - 0.30).abs() < 1e-9);
+        assert_eq!(usage.models, vec!["m1", "m2"]);
+
+        let snapshot = client.reset_usage();
+        assert_eq!(snapshot.input_tokens, 110);
+        assert_eq!(client.usage(), Usage::default());
+
+        client
+            .get_task_status(&TaskId::from("task-2"))
+            .await
+            .unwrap();
+        assert_eq!(client.usage(), Usage::default());
+    }
+
+    #[test]
+    fn test_usage_tracker_forgets_old_task_ids() {
+        let tracker = crate::usage::UsageTracker::default();
+        let status = |n: usize| -> TaskStatus {
+            serde_json::from_value(serde_json::json!({
+                "taskId": format!("task-{}", n),
+                "status": "completed",
+                "metadata": {"usage": {"inputTokens": 1}},
+            }))
+            .unwrap()
+        };
+        let limit = crate::usage::COUNTED_TASK_LIMIT;
+        for n in 0..=limit {
+            assert!(tracker.record(&status(n)).is_some());
+        }
+        assert!(tracker.record(&status(limit)).is_none());
+        // The oldest ID fell out of the window, so memory stays bounded
+        assert!(tracker.record(&status(0)).is_some());
+    }
+
+    // --- Budget Tests ---
+
+    #[tokio::test]
+    async fn test_budget_exceeded() {
+        let mut server = Server::new_async().await;
+        let _run_mock = server
+            .mock("POST", "/run")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let _status_mock = server
+            .mock("GET", "/status/task-1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1", "status": "completed", "metadata": {"usage": {"inputTokens": 80, "outputTokens": 40, "cost": 0.1}}}"#)
+            .create_async()
+            .await;
+
+        let fired = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let fired_clone = fired.clone();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_budget(
+            Budget::default()
+                .max_tokens(100)
+                .on_threshold(0.8, move |status: &BudgetStatus| {
+                    fired_clone.lock().unwrap().push(status.spent_tokens)
+                }),
+        );
+
+        client.run_task("hi", None, None, None).await.unwrap();
+        assert_eq!(*fired.lock().unwrap(), vec![120]);
+        assert!(client.budget_status().unwrap().is_exhausted());
+
+        let res = client.submit_task("again", None).await;
+        assert!(matches!(res, Err(TaskForceAIError::BudgetExceeded(_))));
+
+        let opts = ThreadRunOptions {
+            prompt: "run".to_string(),
+            ..Default::default()
+        };
+        let res = client.run_in_thread(ThreadId(1), opts).await;
+        assert!(matches!(res, Err(TaskForceAIError::BudgetExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn test_budget_window_expires() {
+        let mut server = Server::new_async().await;
+        let _status_mock = server
+            .mock("GET", "/status/task-1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1", "status": "completed", "metadata": {"usage": {"cost": 2.0}}}"#)
+            .create_async()
+            .await;
+        let _run_mock = server
+            .mock("POST", "/run")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-2"}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_budget(
+            Budget::default()
+                .max_cost(1.0)
+                .per(Duration::from_millis(50)),
+        );
+
+        client
+            .get_task_status(&TaskId::from("task-1"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.submit_task("hi", None).await,
+            Err(TaskForceAIError::BudgetExceeded(_))
+        ));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(client.submit_task("hi", None).await.unwrap(), "task-2");
+    }
+
+    // --- Streaming Upload Tests ---
+
+    #[tokio::test]
+    async fn test_upload_file_from_path() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex(
+                "(?s)filename=\"chart.bin\"\\r\\nContent-Type: image/png\\r\\n".to_string(),
+            ))
+            .match_body(Matcher::Regex(
+                "(?s)name=\"purpose\"\\r\\n\\r\\nassistants\\r\\n".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"id": "file-png", "filename": "chart.bin", "purpose": "assistants", "bytes": 12, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chart.bin");
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\nabcd").unwrap();
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let (tx, rx) = tokio::sync::watch::channel(TransferProgress::default());
+        let opts = FileUploadOptions {
+            purpose: Some("assistants".to_string()),
+            progress: Some(tx.into()),
+            ..Default::default()
+        };
+        let file = client
+            .upload_file_from_path(&path, Some(opts))
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-png");
+        assert_eq!(
+            *rx.borrow(),
+            TransferProgress {
+                bytes_transferred: 12,
+                total_bytes: Some(12),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_from_reader_detects_extension() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex(
+                "(?s)Content-Type: text/csv\\r\\n\\r\\na,b\\n1,2\\r\\n".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"id": "file-csv", "filename": "data.csv", "purpose": "p", "bytes": 8, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let reader = std::io::Cursor::new(b"a,b\n1,2".to_vec());
+        let file = client
+            .upload_file_from_reader("data.csv", reader, None, None)
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-csv");
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_from_reader_prefers_extension_for_containers() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex(
+                "Content-Type: application/vnd.openxmlformats-officedocument.wordprocessingml.document\\r\\n".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"id": "file-docx", "filename": "report.docx", "purpose": "p", "bytes": 8, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let reader = std::io::Cursor::new(b"PK\x03\x04docx".to_vec());
+        let file = client
+            .upload_file_from_reader("report.docx", reader, None, None)
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-docx");
+        mock.assert_async().await;
+        assert_eq!(
+            mime::detect("archive.bin", b"PK\x03\x04"),
+            "application/zip"
+        );
+        assert_eq!(mime::detect("photo.txt", b"\xff\xd8\xff"), "image/jpeg");
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_from_path_missing() {
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let res = client
+            .upload_file_from_path("/nonexistent/file.txt", None)
+            .await;
+        assert!(matches!(res, Err(TaskForceAIError::Io(_))));
+    }
+
+    // --- Chunked Upload Tests ---
+
+    fn sha256_hex(data: &[u8]) -> String {
+        use sha2::Digest;
+        format!("{:x}", sha2::Sha256::digest(data))
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_chunked() {
+        let mut server = Server::new_async().await;
+        let _init = server
+            .mock("POST", "/uploads")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"filename": "big.txt", "bytes": 10, "part_size": 4}),
+            ))
+            .with_status(200)
+            .with_body(r#"{"upload_id": "up-1"}"#)
+            .create_async()
+            .await;
+        let mut part_mocks = Vec::new();
+        for (n, chunk) in [&b"0123"[..], b"4567", b"89"].iter().enumerate() {
+            let checksum = sha256_hex(chunk);
+            part_mocks.push(
+                server
+                    .mock("PUT", format!("/uploads/up-1/parts/{}", n + 1).as_str())
+                    .match_header("x-checksum-sha256", checksum.as_str())
+                    .match_body(chunk.to_vec())
+                    .with_status(200)
+                    .with_body(format!(
+                        r#"{{"part_number": {}, "checksum": "{}"}}"#,
+                        n + 1,
+                        checksum
+                    ))
+                    .expect(1)
+                    .create_async()
+                    .await,
+            );
+        }
+        let _complete = server
+            .mock("POST", "/uploads/up-1/complete")
+            .with_status(200)
+            .with_body(r#"{"id": "file-big", "filename": "big.txt", "purpose": "p", "bytes": 10, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.txt");
+        std::fs::write(&path, b"0123456789").unwrap();
+        let token_path = dir.path().join("big.txt.upload");
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            chunked_upload_threshold: Some(5),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let chunked = ChunkedUploadOptions {
+            part_size: Some(4),
+            concurrency: Some(2),
+            resume_token_path: Some(token_path.clone()),
+        };
+        let file = client
+            .upload_file_chunked(&path, None, Some(chunked))
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-big");
+        assert!(!token_path.exists());
+        for mock in part_mocks {
+            mock.assert_async().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_chunked_resumes_from_token() {
+        let mut server = Server::new_async().await;
+        let _init = server
+            .mock("POST", "/uploads")
+            .expect(0)
+            .create_async()
+            .await;
+        let _part1 = server
+            .mock("PUT", "/uploads/up-2/parts/1")
+            .expect(0)
+            .create_async()
+            .await;
+        let part2 = server
+            .mock("PUT", "/uploads/up-2/parts/2")
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"part_number": 2, "checksum": "{}"}}"#,
+                sha256_hex(b"4567")
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let complete = server
+            .mock("POST", "/uploads/up-2/complete")
+            .match_body(Matcher::PartialJson(serde_json::json!({"parts": [
+                {"part_number": 1, "checksum": sha256_hex(b"0123")},
+                {"part_number": 2, "checksum": sha256_hex(b"4567")},
+            ]})))
+            .with_status(200)
+            .with_body(r#"{"id": "file-resumed", "filename": "data.bin", "purpose": "p", "bytes": 8, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"01234567").unwrap();
+        let token_path = dir.path().join("data.bin.upload");
+        ResumeToken {
+            upload_id: "up-2".to_string(),
+            path: path.clone(),
+            filename: "data.bin".to_string(),
+            bytes: 8,
+            modified: std::fs::metadata(&path).unwrap().modified().ok(),
+            part_size: 4,
+            completed_parts: vec![UploadedPart {
+                part_number: 1,
+                checksum: sha256_hex(b"0123"),
+            }],
+        }
+        .save(&token_path)
+        .await
+        .unwrap();
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let chunked = ChunkedUploadOptions {
+            resume_token_path: Some(token_path),
+            ..Default::default()
+        };
+        let file = client
+            .upload_file_chunked(&path, None, Some(chunked))
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-resumed");
+        part2.assert_async().await;
+        complete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_chunked_restarts_when_file_changed() {
+        let mut server = Server::new_async().await;
+        let init = server
+            .mock("POST", "/uploads")
+            .with_status(200)
+            .with_body(r#"{"upload_id": "up/4", "part_size": 8}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let stale = server
+            .mock("PUT", Matcher::Regex("^/uploads/up-2/".to_string()))
+            .expect(0)
+            .create_async()
+            .await;
+        let part = server
+            .mock("PUT", "/uploads/up%2F4/parts/1")
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"part_number": 1, "checksum": "{}"}}"#,
+                sha256_hex(b"abcdefgh")
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let complete = server
+            .mock("POST", "/uploads/up%2F4/complete")
+            .with_status(200)
+            .with_body(r#"{"id": "file-fresh", "filename": "data.bin", "purpose": "p", "bytes": 8, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"abcdefgh").unwrap();
+        let token_path = dir.path().join("data.bin.upload");
+        // Same path and size, but recorded before the file was rewritten
+        let stale_token = ResumeToken {
+            upload_id: "up-2".to_string(),
+            path: path.clone(),
+            filename: "data.bin".to_string(),
+            bytes: 8,
+            modified: Some(std::time::UNIX_EPOCH),
+            part_size: 4,
+            completed_parts: vec![UploadedPart {
+                part_number: 1,
+                checksum: sha256_hex(b"0123"),
+            }],
+        };
+        stale_token.save(&token_path).await.unwrap();
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let err = client
+            .resume_upload(stale_token.clone(), None, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("changed since the upload started"));
+
+        // Hand-edited tokens are refused instead of panicking
+        for malformed in [
+            ResumeToken {
+                part_size: 0,
+                ..stale_token.clone()
+            },
+            ResumeToken {
+                completed_parts: vec![UploadedPart {
+                    part_number: 3,
+                    checksum: sha256_hex(b"0123"),
+                }],
+                ..stale_token.clone()
+            },
+        ] {
+            let err = client
+                .resume_upload(malformed, None, None)
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("invalid resume token"), "{}", err);
+        }
+
+        let chunked = ChunkedUploadOptions {
+            resume_token_path: Some(token_path),
+            ..Default::default()
+        };
+        let file = client
+            .upload_file_chunked(&path, None, Some(chunked))
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-fresh");
+        init.assert_async().await;
+        stale.assert_async().await;
+        part.assert_async().await;
+        complete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_chunked_checksum_mismatch() {
+        let mut server = Server::new_async().await;
+        let _init = server
+            .mock("POST", "/uploads")
+            .with_status(200)
+            .with_body(r#"{"upload_id": "up-3", "part_size": 1024}"#)
+            .create_async()
+            .await;
+        let _part = server
+            .mock("PUT", "/uploads/up-3/parts/1")
+            .with_status(200)
+            .with_body(r#"{"part_number": 1, "checksum": "deadbeef"}"#)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"payload").unwrap();
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            chunked_upload_threshold: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let res = client.upload_file_from_path(&path, None).await;
+        assert!(matches!(
+            res,
+            Err(TaskForceAIError::ChecksumMismatch { actual, .. }) if actual == "deadbeef"
+        ));
+    }
+
+    // --- Download Tests ---
+
+    #[tokio::test]
+    async fn test_download_file_stream() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/files/file-1/content")
+            .with_status(200)
+            .with_body("file content")
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut stream = client
+            .download_file_stream(&FileId::from("file-1"))
+            .await
+            .unwrap();
+        let mut content = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(content, b"file content");
+    }
+
+    #[tokio::test]
+    async fn test_download_file_to_path() {
+        let mut server = Server::new_async().await;
+        let _meta = server
+            .mock("GET", "/files/file-1")
+            .with_status(200)
+            .with_body(r#"{"id": "file-1", "filename": "f1", "purpose": "p", "bytes": 12, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _content = server
+            .mock("GET", "/files/file-1/content")
+            .with_status(200)
+            .with_header("x-checksum-sha256", &sha256_hex(b"file content"))
+            .with_body("file content")
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        let progress = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let progress_clone = progress.clone();
+        let opts = DownloadOptions {
+            progress: Some(ProgressCallback::new(move |p| {
+                progress_clone.lock().unwrap().push(p.bytes_transferred)
+            })),
+            ..Default::default()
+        };
+        let file = client
+            .download_file_to_path(&FileId::from("file-1"), &path, Some(opts))
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-1");
+        assert_eq!(std::fs::read(&path).unwrap(), b"file content");
+        assert!(!dir.path().join("out.txt.part").exists());
+        assert_eq!(progress.lock().unwrap().last(), Some(&12));
+    }
+
+    #[tokio::test]
+    async fn test_download_file_to_path_resume() {
+        let mut server = Server::new_async().await;
+        let _meta = server
+            .mock("GET", "/files/file-1")
+            .with_status(200)
+            .with_body(r#"{"id": "file-1", "filename": "f1", "purpose": "p", "bytes": 12, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _content = server
+            .mock("GET", "/files/file-1/content")
+            .match_header("range", "bytes=5-")
+            .with_status(206)
+            .with_header("x-checksum-sha256", &sha256_hex(b"file content"))
+            .with_body("content")
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        std::fs::write(dir.path().join("out.txt.part"), b"file ").unwrap();
+        let opts = DownloadOptions {
+            resume: true,
+            ..Default::default()
+        };
+        client
+            .download_file_to_path(&FileId::from("file-1"), &path, Some(opts))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"file content");
+    }
+
+    #[tokio::test]
+    async fn test_download_file_to_path_verification_errors() {
+        let mut server = Server::new_async().await;
+        let _meta = server
+            .mock("GET", "/files/file-1")
+            .with_status(200)
+            .with_body(r#"{"id": "file-1", "filename": "f1", "purpose": "p", "bytes": 12, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _content = server
+            .mock("GET", "/files/file-1/content")
+            .with_status(200)
+            .with_header("x-checksum-sha256", "deadbeef")
+            .with_body("file content")
+            .create_async()
+            .await;
+        let _meta2 = server
+            .mock("GET", "/files/file-2")
+            .with_status(200)
+            .with_body(r#"{"id": "file-2", "filename": "f2", "purpose": "p", "bytes": 100, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _content2 = server
+            .mock("GET", "/files/file-2/content")
+            .with_status(200)
+            .with_body("short")
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        let res = client
+            .download_file_to_path(&FileId::from("file-1"), &path, None)
+            .await;
+        assert!(matches!(
+            res,
+            Err(TaskForceAIError::ChecksumMismatch { .. })
+        ));
+        assert!(!path.exists());
+        assert!(!dir.path().join("out.txt.part").exists());
+
+        let res = client
+            .download_file_to_path(&FileId::from("file-2"), &path, None)
+            .await;
+        assert!(matches!(res, Err(TaskForceAIError::Other(msg)) if msg.contains("100 bytes")));
+        assert!(!path.exists());
+    }
+
+    // --- Pagination Tests ---
+
+    #[tokio::test]
+    async fn test_files_paginator() {
+        let mut server = Server::new_async().await;
+        let _page1 = server
+            .mock("GET", "/files?limit=2&offset=0")
+            .with_status(200)
+            .with_body(r#"{"files": [{"id": "file-1", "filename": "f1", "purpose": "p", "bytes": 10, "created_at": 1672531200}, {"id": "file-2", "filename": "f2", "purpose": "p", "bytes": 10, "created_at": 1672531200}], "total": 3}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let _page2 = server
+            .mock("GET", "/files?limit=2&offset=2")
+            .with_status(200)
+            .with_body(r#"{"files": [{"id": "file-3", "filename": "f3", "purpose": "p", "bytes": 10, "created_at": 1672531200}], "total": 3}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let ids: Vec<FileId> = client
+            .files()
+            .with_page_size(2)
+            .map(|f| f.unwrap().id)
+            .collect()
+            .await;
+        assert_eq!(ids, vec!["file-1", "file-2", "file-3"]);
+    }
+
+    #[tokio::test]
+    async fn test_threads_paginator_follows_cursor() {
+        let mut server = Server::new_async().await;
+        let _page1 = server
+            .mock("GET", "/threads")
+            .match_query(Matcher::UrlEncoded("offset".into(), "0".into()))
+            .with_status(200)
+            .with_body(r#"{"threads": [{"id": 1, "title": "t1", "created_at": 1672531200, "updated_at": 1672531200}], "total": 0, "next_cursor": "a+b/c="}"#)
+            .create_async()
+            .await;
+        let _page2 = server
+            .mock("GET", "/threads")
+            .match_query(Matcher::UrlEncoded("cursor".into(), "a+b/c=".into()))
+            .with_status(200)
+            .with_body(r#"{"threads": [{"id": 2, "title": "t2", "created_at": 1672531200, "updated_at": 1672531200}], "total": 0}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let ids: Vec<ThreadId> = client.threads().map(|t| t.unwrap().id).collect().await;
+        assert_eq!(ids, vec![ThreadId(1), ThreadId(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_thread_messages_paginator_error() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut messages = client.thread_messages(ThreadId(1));
+        assert!(matches!(
+            messages.next().await,
+            Some(Err(TaskForceAIError::Api { .. }))
+        ));
+        assert!(messages.next().await.is_none());
+    }
+
+    #[test]
+    fn test_page_request_query() {
+        let request = pagination::PageRequest {
+            limit: 10,
+            offset: 20,
+            cursor: None,
+        };
+        assert_eq!(request.query(), "limit=10&offset=20");
+
+        let request = pagination::PageRequest {
+            cursor: Some("a b&c".to_string()),
+            ..request
+        };
+        assert_eq!(request.query(), "limit=10&cursor=a+b%26c");
+    }
+
+    // --- File Query Tests ---
+
+    #[test]
+    fn test_list_files_query_encoding() {
+        use chrono::TimeZone;
+
+        let query = ListFilesQuery::new()
+            .limit(20)
+            .purpose("assistants")
+            .mime_type("text/csv")
+            .created_after(chrono::Utc.timestamp_opt(1672531200, 0).unwrap())
+            .filename_prefix("q3 report&draft/")
+            .sort_by(FileSortField::CreatedAt, SortOrder::Desc);
+        assert_eq!(
+            query.to_query_string(),
+            "limit=20&purpose=assistants&mime_type=text%2Fcsv&created_after=1672531200&filename_prefix=q3+report%26draft%2F&sort=created_at&order=desc"
+        );
+        assert_eq!(ListFilesQuery::new().to_query_string(), "");
+    }
+
+    #[tokio::test]
+    async fn test_files_matching_paginates_with_filters() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/files")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "50".into()),
+                Matcher::UrlEncoded("offset".into(), "0".into()),
+                Matcher::UrlEncoded("purpose".into(), "assistants".into()),
+                Matcher::UrlEncoded("filename_prefix".into(), "a b".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"files": [{"id": "file-1", "filename": "a b.txt", "purpose": "assistants", "bytes": 10, "created_at": 1672531200}], "total": 1}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let query = ListFilesQuery::new()
+            .purpose("assistants")
+            .filename_prefix("a b");
+        let files: Vec<File> = client
+            .files_matching(query)
+            .map(|f| f.unwrap())
+            .collect()
+            .await;
+        assert_eq!(files.len(), 1);
+    }
+
+    // --- Attachment Tests ---
+
+    #[tokio::test]
+    async fn test_submit_task_with_file_ids() {
+        let mut server = Server::new_async().await;
+        let _file = server
+            .mock("GET", "/files/file-1")
+            .with_status(200)
+            .with_body(r#"{"id": "file-1", "filename": "doc.pdf", "purpose": "assistants", "bytes": 10, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _run = server
+            .mock("POST", "/run")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"options": {"fileIds": ["file-1"]}}),
+            ))
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-files"}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = TaskSubmissionOptions {
+            file_ids: Some(vec![FileId::from("file-1")]),
+            ..Default::default()
+        };
+        let task_id = client.submit_task("summarize", Some(opts)).await.unwrap();
+        assert_eq!(task_id, "task-files");
+    }
+
+    #[tokio::test]
+    async fn test_attachment_purpose_validation() {
+        let mut server = Server::new_async().await;
+        let _file = server
+            .mock("GET", "/files/file-2")
+            .with_status(200)
+            .with_body(r#"{"id": "file-2", "filename": "x.jsonl", "purpose": "fine-tune", "bytes": 10, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = ThreadRunOptions {
+            prompt: "run".to_string(),
+            file_ids: Some(vec![FileId::from("file-2")]),
+            ..Default::default()
+        };
+        let res = client.run_in_thread(ThreadId(1), opts).await;
+        assert!(
+            matches!(res, Err(TaskForceAIError::InvalidAttachment(msg)) if msg.contains("fine-tune"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_in_thread_with_files() {
+        let mut server = Server::new_async().await;
+        let upload = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex(
+                "(?s)name=\"purpose\"\\r\\n\\r\\nassistants\\r\\n".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"id": "file-up", "filename": "notes.txt", "purpose": "assistants", "bytes": 5, "created_at": 1672531200}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        // Just-uploaded files are known to be attachable
+        let lookup = server
+            .mock("GET", "/files/file-up")
+            .expect(0)
+            .create_async()
+            .await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"prompt": "read this", "file_ids": ["file-up"]}),
+            ))
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 101}"#)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = ThreadRunOptions {
+            prompt: "read this".to_string(),
+            ..Default::default()
+        };
+        let res = client
+            .run_in_thread_with_files(ThreadId(1), &[&path], opts)
+            .await
+            .unwrap();
+        assert_eq!(res.task_id, "task-t1");
+
+        // Requests that would fail anyway upload nothing
+        let opts = ThreadRunOptions {
+            prompt: " ".to_string(),
+            ..Default::default()
+        };
+        let err = client
+            .run_in_thread_with_files(ThreadId(1), &[&path], opts)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "EmptyPrompt");
+        let opts = TaskSubmissionOptions {
+            temperature: Some(9.0),
+            ..Default::default()
+        };
+        let err = client
+            .submit_task_with_files("read this", &[&path], Some(opts))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "InvalidOptions");
+        upload.assert_async().await;
+        lookup.assert_async().await;
+    }
+
+    // --- Image Attachment Tests ---
+
+    const PNG_1X1: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
+        0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8,
+        0xcf, 0xc0, 0xf0, 0x1f, 0x00, 0x05, 0x00, 0x01, 0xff, 0x89, 0x99, 0x3d, 0x1d, 0x00, 0x00,
+        0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[tokio::test]
+    async fn test_image_attachment_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pixel.dat");
+        std::fs::write(&path, PNG_1X1).unwrap();
+
+        let image = ImageAttachment::from_path(&path).await.unwrap();
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(image.name.as_deref(), Some("pixel.dat"));
+        assert!(image.data.starts_with("iVBORw0KGgo"));
+    }
+
+    #[tokio::test]
+    async fn test_image_attachment_from_url_fetch() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/img/pixel.png")
+            .with_status(200)
+            .with_body(PNG_1X1)
+            .create_async()
+            .await;
+
+        let image = ImageAttachment::from_url_fetch(&format!("{}/img/pixel.png", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(image.name.as_deref(), Some("pixel.png"));
+    }
+
+    #[tokio::test]
+    async fn test_image_attachment_from_url_fetch_stops_at_size_limit() {
+        let mut server = Server::new_async().await;
+        let mut oversized = PNG_1X1.to_vec();
+        oversized.resize(crate::images::MAX_IMAGE_BYTES + 1, 0);
+        let _declared = server
+            .mock("GET", "/img/declared.png")
+            .with_status(200)
+            .with_body(oversized)
+            .create_async()
+            .await;
+        // No Content-Length: the body is cut off once it passes the limit
+        let _chunked = server
+            .mock("GET", "/img/chunked.png")
+            .with_status(200)
+            .with_chunked_body(|w| {
+                w.write_all(PNG_1X1)?;
+                let block = vec![0; 1024 * 1024];
+                for _ in 0..64 {
+                    w.write_all(&block)?;
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+
+        for path in ["declared.png", "chunked.png"] {
+            let res =
+                ImageAttachment::from_url_fetch(&format!("{}/img/{}", server.url(), path)).await;
+            assert!(
+                matches!(&res, Err(TaskForceAIError::InvalidAttachment(msg)) if msg.contains("limit")),
+                "{}: {:?}",
+                path,
+                res.err()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_image_attachment_validation() {
+        let res = ImageAttachment::from_bytes(b"%PDF-1.7", Some("doc.pdf".to_string()));
+        assert!(
+            matches!(res, Err(TaskForceAIError::InvalidAttachment(msg)) if msg.contains("doc.pdf"))
+        );
+
+        let image = ImageAttachment::from_bytes(PNG_1X1, None)
+            .unwrap()
+            .with_detail("ultra");
+        assert!(image.validate().is_err());
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let opts = TaskSubmissionOptions {
+            images: Some(vec![ImageAttachment {
+                data: "not base64!".to_string(),
+                mime_type: "image/png".to_string(),
+                name: None,
+                detail: None,
+            }]),
+            ..Default::default()
+        };
+        let res = client.submit_task("describe", Some(opts)).await;
+        assert!(matches!(res, Err(TaskForceAIError::InvalidAttachment(_))));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_image_attachment_downscales_to_budget() {
+        use base64::Engine;
+
+        let source = image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8, y as u8, 0]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(source)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let budget = ImageBudget {
+            max_pixels: Some(16 * 16),
+            max_bytes: None,
+        };
+        let attachment = ImageAttachment::from_bytes_with_budget(&png, None, &budget).unwrap();
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(&attachment.data)
+            .unwrap();
+        let scaled = image::load_from_memory(&decoded).unwrap();
+        assert_eq!((scaled.width(), scaled.height()), (16, 16));
+        assert_eq!(attachment.mime_type, "image/png");
+    }
+
+    // --- Directory Sync Tests ---
+
+    fn sync_fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"alpha").unwrap();
+        std::fs::write(dir.path().join("c.txt"), b"gamma v2").unwrap();
+        std::fs::write(dir.path().join("docs").join("b.txt"), b"beta").unwrap();
+        let manifest = serde_json::json!({
+            "files": {
+                "a.txt": {"file_id": "file-a", "sha256": sha256_hex(b"alpha"), "bytes": 5},
+                "c.txt": {"file_id": "file-c-old", "sha256": sha256_hex(b"gamma"), "bytes": 5},
+                "gone.txt": {"file_id": "file-orphan", "sha256": sha256_hex(b"old"), "bytes": 3},
+            }
+        });
+        std::fs::write(
+            dir.path().join(sync::DEFAULT_MANIFEST_NAME),
+            manifest.to_string(),
+        )
+        .unwrap();
+        dir
+    }
+
+    const SYNC_LISTING: &str = r#"{"files": [
+        {"id": "file-a", "filename": "a.txt", "purpose": "kb", "bytes": 5, "created_at": 1672531200},
+        {"id": "file-c-old", "filename": "c.txt", "purpose": "kb", "bytes": 5, "created_at": 1672531200},
+        {"id": "file-orphan", "filename": "gone.txt", "purpose": "kb", "bytes": 3, "created_at": 1672531200},
+        {"id": "file-other", "filename": "other.txt", "purpose": "assistants", "bytes": 3, "created_at": 1672531200}
+    ], "total": 4}"#;
+
+    #[tokio::test]
+    async fn test_sync_directory_uploads_changes_and_deletes_orphans() {
+        let mut server = Server::new_async().await;
+        let _list = server
+            .mock("GET", "/files")
+            .match_query(Matcher::UrlEncoded("purpose".into(), "kb".into()))
+            .with_status(200)
+            .with_body(SYNC_LISTING)
+            .create_async()
+            .await;
+        let upload_b = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex("filename=\"docs/b.txt\"".to_string()))
+            .with_status(200)
+            .with_body(r#"{"id": "file-b", "filename": "docs/b.txt", "purpose": "kb", "bytes": 4, "created_at": 1672531200}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let upload_c = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex("filename=\"c.txt\"".to_string()))
+            .with_status(200)
+            .with_body(r#"{"id": "file-c-new", "filename": "c.txt", "purpose": "kb", "bytes": 8, "created_at": 1672531200}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let delete_old = server
+            .mock("DELETE", "/files/file-c-old")
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+        let delete_orphan = server
+            .mock("DELETE", "/files/file-orphan")
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let dir = sync_fixture();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = SyncOptions {
+            delete_orphans: true,
+            ..Default::default()
+        };
+        let report = client
+            .sync_directory(dir.path(), "kb", Some(opts))
+            .await
+            .unwrap();
+
+        upload_b.assert_async().await;
+        upload_c.assert_async().await;
+        delete_old.assert_async().await;
+        delete_orphan.assert_async().await;
+        assert_eq!(report.unchanged, vec!["a.txt"]);
+        assert_eq!(report.uploaded.len(), 1);
+        assert_eq!(report.uploaded[0].path, "docs/b.txt");
+        assert_eq!(report.updated.len(), 1);
+        assert_eq!(report.updated[0].file_id, Some(FileId::from("file-c-new")));
+        let deleted: Vec<&str> = report.deleted.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(deleted, vec!["file-c-old", "file-orphan"]);
+        assert!(report.orphans.is_empty());
+        assert!(report.failed.is_empty());
+
+        let manifest: SyncManifest = serde_json::from_slice(
+            &std::fs::read(dir.path().join(sync::DEFAULT_MANIFEST_NAME)).unwrap(),
+        )
+        .unwrap();
+        let ids: Vec<&str> = manifest
+            .files
+            .values()
+            .map(|e| e.file_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["file-a", "file-c-new", "file-b"]);
+        assert_eq!(manifest.files["c.txt"].sha256, sha256_hex(b"gamma v2"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_directory_dry_run_changes_nothing() {
+        let mut server = Server::new_async().await;
+        let _list = server
+            .mock("GET", "/files")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(SYNC_LISTING)
+            .create_async()
+            .await;
+        let upload = server.mock("POST", "/files").expect(0).create_async().await;
+        let delete = server
+            .mock("DELETE", Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let dir = sync_fixture();
+        let manifest_path = dir.path().join(sync::DEFAULT_MANIFEST_NAME);
+        let before = std::fs::read(&manifest_path).unwrap();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let report = client
+            .sync_directory(
+                dir.path(),
+                "kb",
+                Some(SyncOptions {
+                    dry_run: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+
+        upload.assert_async().await;
+        delete.assert_async().await;
+        assert!(report.dry_run);
+        assert_eq!(report.uploaded[0].file_id, None);
+        assert_eq!(report.updated[0].path, "c.txt");
+        let orphans: Vec<&str> = report.orphans.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(orphans, vec!["file-c-old", "file-orphan"]);
+        assert_eq!(std::fs::read(&manifest_path).unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn test_sync_directory_remembers_orphans_until_deleted() {
+        let mut server = Server::new_async().await;
+        let _list = server
+            .mock("GET", "/files")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{"files": [
+                {"id": "file-a", "filename": "a.txt", "purpose": "kb", "bytes": 5, "created_at": 1672531200},
+                {"id": "file-b", "filename": "docs/b.txt", "purpose": "kb", "bytes": 4, "created_at": 1672531200},
+                {"id": "file-c-new", "filename": "c.txt", "purpose": "kb", "bytes": 8, "created_at": 1672531200},
+                {"id": "file-c-old", "filename": "c.txt", "purpose": "kb", "bytes": 5, "created_at": 1672531200},
+                {"id": "file-orphan", "filename": "gone.txt", "purpose": "kb", "bytes": 3, "created_at": 1672531200}
+            ], "total": 5}"#)
+            .create_async()
+            .await;
+        let upload_b = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex("filename=\"docs/b.txt\"".to_string()))
+            .with_status(200)
+            .with_body(r#"{"id": "file-b", "filename": "docs/b.txt", "purpose": "kb", "bytes": 4, "created_at": 1672531200}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let upload_c = server
+            .mock("POST", "/files")
+            .match_body(Matcher::Regex("filename=\"c.txt\"".to_string()))
+            .with_status(200)
+            .with_body(r#"{"id": "file-c-new", "filename": "c.txt", "purpose": "kb", "bytes": 8, "created_at": 1672531200}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let delete_old = server
+            .mock("DELETE", "/files/file-c-old")
+            .with_status(200)
+            .with_body("{}")
+            .expect(1)
+            .create_async()
+            .await;
+        let delete_orphan = server
+            .mock("DELETE", "/files/file-orphan")
+            .with_status(200)
+            .with_body("{}")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let dir = sync_fixture();
+        let manifest_path = dir.path().join(sync::DEFAULT_MANIFEST_NAME);
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let report = client.sync_directory(dir.path(), "kb", None).await.unwrap();
+        assert_eq!(report.orphans.len(), 2);
+        let manifest: SyncManifest =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest.orphans, vec!["file-c-old", "file-orphan"]);
+
+        // The orphans are gone from `files` but still deleted by a later run
+        let opts = SyncOptions {
+            delete_orphans: true,
+            ..Default::default()
+        };
+        let report = client
+            .sync_directory(dir.path(), "kb", Some(opts))
+            .await
+            .unwrap();
+        upload_b.assert_async().await;
+        upload_c.assert_async().await;
+        delete_old.assert_async().await;
+        delete_orphan.assert_async().await;
+        assert_eq!(report.unchanged, vec!["a.txt", "c.txt", "docs/b.txt"]);
+        let deleted: Vec<&str> = report.deleted.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(deleted, vec!["file-c-old", "file-orphan"]);
+        let manifest: SyncManifest =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        assert!(manifest.orphans.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_directory_keeps_unreadable_and_foreign_files() {
+        let mut server = Server::new_async().await;
+        let _list = server
+            .mock("GET", "/files")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(
+                r#"{"files": [
+                    {"id": "file-z", "filename": "z.txt", "purpose": "kb", "bytes": 4, "created_at": 1672531200},
+                    {"id": "file-foreign", "filename": "notes.pdf", "purpose": "kb", "bytes": 9, "created_at": 1672531200}
+                ], "total": 2}"#,
+            )
+            .create_async()
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"alpha").unwrap();
+        std::fs::write(dir.path().join("z.txt"), b"zeta").unwrap();
+        let manifest_path = dir.path().join(sync::DEFAULT_MANIFEST_NAME);
+        std::fs::write(
+            &manifest_path,
+            serde_json::json!({"files": {
+                "z.txt": {"file_id": "file-z", "sha256": sha256_hex(b"zeta"), "bytes": 4},
+            }})
+            .to_string(),
+        )
+        .unwrap();
+
+        // z.txt disappears while a.txt is uploading, so hashing it fails
+        let vanishing = dir.path().join("z.txt");
+        let upload = server
+            .mock("POST", "/files")
+            .with_status(200)
+            .with_body_from_request(move |_| {
+                let _ = std::fs::remove_file(&vanishing);
+                br#"{"id": "file-a", "filename": "a.txt", "purpose": "kb", "bytes": 5, "created_at": 1672531200}"#.to_vec()
+            })
+            .expect(1)
+            .create_async()
+            .await;
+        let delete = server
+            .mock("DELETE", Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let opts = SyncOptions {
+            delete_orphans: true,
+            ..Default::default()
+        };
+        let report = client
+            .sync_directory(dir.path(), "kb", Some(opts))
+            .await
+            .unwrap();
+
+        upload.assert_async().await;
+        delete.assert_async().await;
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, "z.txt");
+        assert!(report.deleted.is_empty() && report.orphans.is_empty());
+        let manifest: SyncManifest =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest.files["z.txt"].file_id, "file-z");
+        assert_eq!(manifest.files["a.txt"].file_id, "file-a");
+
+        // Pruning files the manifest never recorded is opt-in
+        let report = client
+            .sync_directory(
+                dir.path(),
+                "kb",
+                Some(SyncOptions {
+                    dry_run: true,
+                    prune_unmanaged: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+        let orphans: Vec<&str> = report.orphans.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(orphans, vec!["file-foreign", "file-z"]);
+    }
+
+    // --- Thread Message Content Tests ---
+
+    #[test]
+    fn test_role_round_trip() {
+        for (raw, role) in [
+            ("user", Role::User),
+            ("assistant", Role::Assistant),
+            ("system", Role::System),
+            ("tool", Role::Tool),
+            ("critic", Role::Unknown("critic".to_string())),
+        ] {
+            let parsed: Role = serde_json::from_value(serde_json::json!(raw)).unwrap();
+            assert_eq!(parsed, role);
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                serde_json::json!(raw)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multimodal_thread_messages_round_trip() {
+        let content = serde_json::json!([
+            {"type": "text", "text": "What is in this chart?"},
+            {"type": "image", "data": "aGk=", "mime_type": "image/png", "detail": "high"},
+            {"type": "file", "file_id": "file-1", "filename": "report.pdf"},
+            {"type": "tool_call", "id": "call-1", "name": "search", "arguments": {"q": "sales"}},
+            {"type": "tool_result", "tool_call_id": "call-1", "content": "42", "is_error": true},
+            {"type": "audio", "url": "https://example.com/a.mp3"}
+        ]);
+        let message = serde_json::json!({
+            "id": 100,
+            "thread_id": 1,
+            "role": "user",
+            "content": content,
+            "created_at": 1672531200,
+            "metadata": {"source": "web"}
+        });
+
+        let mut server = Server::new_async().await;
+        let create_mock = server
+            .mock("POST", "/threads")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"messages": [message.clone()]}),
+            ))
+            .with_status(200)
+            .with_body(r#"{"id": 1, "title": "Charts", "created_at": 1672531200, "updated_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _messages_mock = server
+            .mock("GET", "/threads/1/messages?limit=10&offset=0")
+            .with_status(200)
+            .with_body(serde_json::json!({"messages": [message.clone()], "total": 1}).to_string())
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let res = client
+            .get_thread_messages(ThreadId(1), 10, 0)
+            .await
+            .unwrap();
+        let fetched = &res.messages[0];
+        assert_eq!(fetched.role, Role::User);
+        assert_eq!(fetched.content.text(), "What is in this chart?");
+        let parts = fetched.content.parts();
+        assert_eq!(parts.len(), 6);
+        assert!(matches!(&parts[1], ContentPart::Image(image) if image.mime_type == "image/png"));
+        assert!(matches!(&parts[3], ContentPart::ToolCall(call) if call.name == "search"));
+        assert!(matches!(&parts[4], ContentPart::ToolResult(result) if result.is_error));
+        assert!(matches!(&parts[5], ContentPart::Other(_)));
+        assert_eq!(serde_json::to_value(fetched).unwrap(), message);
+
+        client
+            .create_thread(Some(CreateThreadOptions {
+                messages: Some(res.messages.clone()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        create_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_update_thread() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("PATCH", "/threads/1")
+            .match_body(Matcher::Json(
+                serde_json::json!({"title": "Renamed", "metadata": {"pinned": true}}),
+            ))
+            .with_status(200)
+            .with_body(r#"{"id": 1, "title": "Renamed", "created_at": 1672531200, "updated_at": 1672531300, "metadata": {"pinned": true}}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let thread = client
+            .update_thread(
+                ThreadId(1),
+                UpdateThreadOptions {
+                    title: Some("Renamed".to_string()),
+                    metadata: Some(
+                        [("pinned".to_string(), serde_json::json!(true))]
+                            .into_iter()
+                            .collect(),
+                    ),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(thread.title, "Renamed");
+        assert_eq!(thread.metadata.unwrap()["pinned"], serde_json::json!(true));
+    }
+
+    #[tokio::test]
+    async fn test_edit_and_delete_thread_message() {
+        let mut server = Server::new_async().await;
+        let _edit = server
+            .mock("PATCH", "/threads/1/messages/100")
+            .match_body(Matcher::Json(serde_json::json!({"content": "[redacted]"})))
+            .with_status(200)
+            .with_body(r#"{"id": 100, "thread_id": 1, "role": "user", "content": "[redacted]", "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let delete = server
+            .mock("DELETE", "/threads/1/messages/101")
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let message = client
+            .edit_thread_message(ThreadId(1), MessageId(100), "[redacted]")
+            .await
+            .unwrap();
+        assert_eq!(message.content.text(), "[redacted]");
+
+        client
+            .delete_thread_message(ThreadId(1), MessageId(101))
+            .await
+            .unwrap();
+        delete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_run_in_thread_and_wait_reads_only_the_tail() {
+        let mut server = Server::new_async().await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 217}"#)
+            .create_async()
+            .await;
+        let _status = server
+            .mock("GET", "/status/task-t1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-t1", "status": "completed", "result": "Done"}"#)
+            .create_async()
+            .await;
+        let page = |from: i64, count: i64| {
+            let messages: Vec<serde_json::Value> = (from..from + count)
+                .map(|id| {
+                    let role = if id % 2 == 0 { "assistant" } else { "user" };
+                    serde_json::json!({"id": id, "thread_id": 1, "role": role, "content": format!("m{}", id), "created_at": 1672531200})
+                })
+                .collect();
+            serde_json::json!({"messages": messages, "total": 120}).to_string()
+        };
+        let head = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(page(100, 50))
+            .expect(1)
+            .create_async()
+            .await;
+        let middle = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=50")
+            .expect(0)
+            .create_async()
+            .await;
+        let tail = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=70")
+            .with_status(200)
+            .with_body(page(170, 50))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = ThreadRunOptions {
+            prompt: "Hi".to_string(),
+            ..Default::default()
+        };
+        let result = client
+            .run_in_thread_and_wait(ThreadId(1), opts, Some(Duration::from_millis(1)), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(result.message.id, 218);
+        head.assert_async().await;
+        middle.assert_async().await;
+        tail.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_run_in_thread_and_wait() {
+        let mut server = Server::new_async().await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 101}"#)
+            .create_async()
+            .await;
+        let _status = server
+            .mock("GET", "/status/task-t1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-t1", "status": "completed", "result": "Hello!"}"#)
+            .create_async()
+            .await;
+        let _messages = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 100, "thread_id": 1, "role": "assistant", "content": "Earlier reply", "created_at": 1672531200},
+                {"id": 101, "thread_id": 1, "role": "user", "content": "Hi", "created_at": 1672531300},
+                {"id": 102, "thread_id": 1, "role": "assistant", "content": "Hello!", "created_at": 1672531310}
+            ], "total": 3}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = ThreadRunOptions {
+            prompt: "Hi".to_string(),
+            ..Default::default()
+        };
+        let result = client
+            .run_in_thread_and_wait(ThreadId(1), opts, Some(Duration::from_millis(1)), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(result.status.result.as_deref(), Some("Hello!"));
+        assert_eq!(result.message.id, 102);
+        assert_eq!(result.message.content.text(), "Hello!");
+    }
+
+    #[tokio::test]
+    async fn test_run_in_thread_and_wait_missing_reply() {
+        let mut server = Server::new_async().await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 101}"#)
+            .create_async()
+            .await;
+        let _status = server
+            .mock("GET", "/status/task-t1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-t1", "status": "completed"}"#)
+            .create_async()
+            .await;
+        let _messages = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [{"id": 101, "thread_id": 1, "role": "user", "content": "Hi", "created_at": 1672531300}], "total": 1}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = ThreadRunOptions {
+            prompt: "Hi".to_string(),
+            ..Default::default()
+        };
+        let err = client
+            .run_in_thread_and_wait(ThreadId(1), opts, Some(Duration::from_millis(1)), Some(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskForceAIError::Other(_)));
+    }
+
+    #[tokio::test]
+    async fn test_run_in_thread_stream() {
+        let mut server = Server::new_async().await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 101}"#)
+            .create_async()
+            .await;
+        let _stream = server
+            .mock("GET", "/stream/task-t1")
+            .with_status(200)
+            .with_body(
+                "data: {\"taskId\": \"task-t1\", \"status\": \"processing\", \"result\": \"Hel\"}\n\
+                 data: {\"taskId\": \"task-t1\", \"status\": \"completed\", \"result\": \"Hello!\"}\n",
+            )
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let opts = ThreadRunOptions {
+            prompt: "Hi".to_string(),
+            ..Default::default()
+        };
+        let updates: Vec<TaskStatus> = client
+            .run_in_thread_stream(ThreadId(1), opts)
+            .await
+            .unwrap()
+            .map(|update| update.unwrap())
+            .collect()
+            .await;
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].result.as_deref(), Some("Hel"));
+        assert_eq!(updates[1].status, TaskStatusValue::Completed);
+    }
+
+    // --- Conversation Tests ---
+
+    fn message(id: i64, role: Role, text: &str) -> ThreadMessage {
+        ThreadMessage {
+            id: MessageId(id),
+            thread_id: ThreadId(1),
+            role,
+            content: text.into(),
+            created_at: chrono::Utc::now(),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context_strategies() {
+        let history = vec![
+            message(1, Role::System, "Be brief."),
+            message(2, Role::User, "aaaaaaaa"),
+            message(3, Role::Assistant, "bbbbbbbb"),
+            message(4, Role::User, "cccccccc"),
+        ];
+        let ids = |messages: Vec<ThreadMessage>| messages.iter().map(|m| m.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(KeepAll.select(&history).await.unwrap()), [1, 2, 3, 4]);
+        assert_eq!(
+            ids(TrimToTokens::new(4).select(&history).await.unwrap()),
+            [3, 4]
+        );
+        assert_eq!(
+            ids(PinSystemMessages::new(TrimToTokens::new(2))
+                .select(&history)
+                .await
+                .unwrap()),
+            [1, 4]
+        );
+
+        let summarized = SummarizeOlder::new(1, |older| format!("{} messages", older.len()))
+            .select(&history)
+            .await
+            .unwrap();
+        assert_eq!(summarized.len(), 2);
+        assert_eq!(summarized[0].role, Role::System);
+        assert_eq!(
+            summarized[0].content.text(),
+            "Summary of earlier conversation: 3 messages"
+        );
+        assert_eq!(summarized[1].id, 4);
+    }
+
+    #[tokio::test]
+    async fn test_local_conversation_send() {
+        let mut server = Server::new_async().await;
+        let _first = server
+            .mock("POST", "/run")
+            .match_body(Matcher::PartialJson(serde_json::json!({"prompt": "Hi"})))
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1"}"#)
+            .create_async()
+            .await;
+        let _second = server
+            .mock("POST", "/run")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "prompt": "user: Hi\n\nassistant: Hello!\n\nuser: Bye"
+            })))
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-2"}"#)
+            .create_async()
+            .await;
+        let _status1 = server
+            .mock("GET", "/status/task-1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1", "status": "completed", "result": "Hello!"}"#)
+            .create_async()
+            .await;
+        let _status2 = server
+            .mock("GET", "/status/task-2")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-2", "status": "completed", "result": "Goodbye!"}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut conversation = client.conversation();
+        let reply = conversation.send("Hi").await.unwrap();
+        assert_eq!(reply.content.text(), "Hello!");
+        let reply = conversation.send("Bye").await.unwrap();
+        assert_eq!(reply.content.text(), "Goodbye!");
+
+        let roles: Vec<_> = conversation
+            .messages()
+            .iter()
+            .map(|m| m.role.clone())
+            .collect();
+        assert_eq!(
+            roles,
+            [Role::User, Role::Assistant, Role::User, Role::Assistant]
+        );
+        assert_eq!(conversation.thread_id(), None);
+    }
+
+    #[tokio::test]
+    async fn test_thread_conversation_send_with_trimmed_context() {
+        let mut server = Server::new_async().await;
+        let _initial = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 1, "thread_id": 1, "role": "user", "content": "A long earlier question", "created_at": 1672531200},
+                {"id": 2, "thread_id": 1, "role": "assistant", "content": "Ok", "created_at": 1672531210}
+            ], "total": 2}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let run = server
+            .mock("POST", "/threads/1/runs")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "prompt": "Next",
+                "options": {"context": [{"id": 2, "role": "assistant", "content": "Ok"}]}
+            })))
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 3}"#)
+            .create_async()
+            .await;
+        let _status = server
+            .mock("GET", "/status/task-t1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-t1", "status": "completed", "result": "Done"}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut conversation = client
+            .thread_conversation(ThreadId(1))
+            .await
+            .unwrap()
+            .with_strategy(TrimToTokens::new(1));
+        assert_eq!(conversation.messages().len(), 2);
+
+        let updated = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 1, "thread_id": 1, "role": "user", "content": "A long earlier question", "created_at": 1672531200},
+                {"id": 2, "thread_id": 1, "role": "assistant", "content": "Ok", "created_at": 1672531210},
+                {"id": 3, "thread_id": 1, "role": "user", "content": "Next", "created_at": 1672531220},
+                {"id": 4, "thread_id": 1, "role": "assistant", "content": "Done", "created_at": 1672531230}
+            ], "total": 4}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let reply = conversation.send("Next").await.unwrap();
+        assert_eq!(reply.id, 4);
+        let ids: Vec<i64> = conversation.messages().iter().map(|m| m.id.0).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert_eq!(conversation.messages()[2].content.text(), "Next");
+        run.assert_async().await;
+        updated.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_conversation_send_stream_records_reply() {
+        let mut server = Server::new_async().await;
+        let _run = server
+            .mock("POST", "/run")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-1"}"#)
+            .create_async()
+            .await;
+        let _stream = server
+            .mock("GET", "/stream/task-1")
+            .with_status(200)
+            .with_body(
+                "data: {\"taskId\": \"task-1\", \"status\": \"processing\"}\n\
+                 data: {\"taskId\": \"task-1\", \"status\": \"completed\", \"result\": \"Hello!\"}\n",
+            )
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut conversation = client.conversation();
+        conversation.push(Role::System, "Be brief.");
+        let updates = conversation
+            .send_stream("Hi")
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(updates.len(), 2);
+
+        let messages = conversation.messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].content.text(), "Hi");
+        assert_eq!(messages[2].role, Role::Assistant);
+        assert_eq!(messages[2].content.text(), "Hello!");
+    }
+
+    // --- Branching Tests ---
+
+    #[tokio::test]
+    async fn test_fork_thread_copies_messages_and_records_lineage() {
+        let mut server = Server::new_async().await;
+        let _parent = server
+            .mock("GET", "/threads/1")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "title": "Research", "created_at": 1672531200, "updated_at": 1672531200}"#)
+            .create_async()
+            .await;
+        let _messages = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 10, "thread_id": 1, "role": "user", "content": "Q1", "created_at": 1672531200},
+                {"id": 11, "thread_id": 1, "role": "assistant", "content": "A1", "created_at": 1672531210},
+                {"id": 12, "thread_id": 1, "role": "user", "content": "Q2", "created_at": 1672531220}
+            ], "total": 3}"#)
+            .create_async()
+            .await;
+        let create = server
+            .mock("POST", "/threads")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "title": "Research",
+                "messages": [{"id": 10}, {"id": 11}],
+                "metadata": {"parent_thread_id": 1, "branch_message_id": 11, "root_thread_id": 1}
+            })))
+            .with_status(200)
+            .with_body(r#"{"id": 2, "title": "Research", "created_at": 1672531300, "updated_at": 1672531300,
+                "metadata": {"parent_thread_id": 1, "branch_message_id": 11, "root_thread_id": 1}}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let branch = client
+            .fork_thread(ThreadId(1), MessageId(11), None)
+            .await
+            .unwrap();
+        create.assert_async().await;
+        assert_eq!(branch.parent_thread_id(), Some(ThreadId(1)));
+        assert_eq!(branch.branch_message_id(), Some(MessageId(11)));
+        assert_eq!(branch.root_thread_id(), 1);
+
+        let err = client
+            .fork_thread(ThreadId(1), MessageId(99), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskForceAIError::Other(_)));
+    }
+
+    #[tokio::test]
+    async fn test_thread_tree_lists_all_branches() {
+        let mut server = Server::new_async().await;
+        let _branch = server
+            .mock("GET", "/threads/3")
+            .with_status(200)
+            .with_body(r#"{"id": 3, "title": "B2", "created_at": 1672531400, "updated_at": 1672531400,
+                "metadata": {"parent_thread_id": 2, "branch_message_id": 21, "root_thread_id": 1}}"#)
+            .create_async()
+            .await;
+        let _root = server
+            .mock("GET", "/threads/1")
+            .with_status(200)
+            .with_body(
+                r#"{"id": 1, "title": "Root", "created_at": 1672531200, "updated_at": 1672531200}"#,
+            )
+            .create_async()
+            .await;
+        let _threads = server
+            .mock("GET", "/threads?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"threads": [
+                {"id": 1, "title": "Root", "created_at": 1672531200, "updated_at": 1672531200},
+                {"id": 2, "title": "B1", "created_at": 1672531300, "updated_at": 1672531300,
+                    "metadata": {"parent_thread_id": 1, "branch_message_id": 11, "root_thread_id": 1}},
+                {"id": 3, "title": "B2", "created_at": 1672531400, "updated_at": 1672531400,
+                    "metadata": {"parent_thread_id": 2, "branch_message_id": 21, "root_thread_id": 1}},
+                {"id": 4, "title": "B3", "created_at": 1672531500, "updated_at": 1672531500,
+                    "metadata": {"parent_thread_id": 1, "branch_message_id": 10, "root_thread_id": 1}},
+                {"id": 5, "title": "Unrelated", "created_at": 1672531600, "updated_at": 1672531600}
+            ], "total": 5}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let tree = client.thread_tree(ThreadId(3)).await.unwrap();
+        assert_eq!(tree.thread.id, 1);
+        assert_eq!(tree.thread_count(), 4);
+        assert_eq!(tree.paths(), vec![vec![1, 4], vec![1, 2, 3]]);
+        assert_eq!(tree.children[0].branched_at, Some(MessageId(10)));
+    }
+
+    // --- Transcript Tests ---
+
+    fn sample_transcript() -> ThreadTranscript {
+        let created_at = chrono::DateTime::from_timestamp(1672531200, 0).unwrap();
+        ThreadTranscript {
+            thread: Thread {
+                id: ThreadId(1),
+                title: "Support".to_string(),
+                created_at,
+                updated_at: created_at,
+                metadata: Some(
+                    [("team".to_string(), serde_json::json!("billing"))]
+                        .into_iter()
+                        .collect(),
+                ),
+            },
+            messages: vec![
+                ThreadMessage {
+                    created_at,
+                    ..message(10, Role::User, "My invoice is wrong.\n\nCan you check?")
+                },
+                ThreadMessage {
+                    created_at,
+                    ..message(11, Role::Assistant, "Fixed.")
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_transcript_formats_round_trip() {
+        let transcript = sample_transcript();
+        for format in [ExportFormat::Json, ExportFormat::Jsonl] {
+            let rendered = transcript.render(format).unwrap();
+            assert_eq!(
+                ThreadTranscript::parse(&rendered, format).unwrap(),
+                transcript
+            );
+        }
+
+        let markdown = transcript.render(ExportFormat::Markdown).unwrap();
+        assert!(
+            markdown.starts_with("# Support\n\n- Thread ID: 1\n- Created: 2023-01-01T00:00:00Z")
+        );
+        assert!(markdown.contains("### user · 2023-01-01T00:00:00Z\n\nMy invoice is wrong."));
+        let parsed = ThreadTranscript::parse(&markdown, ExportFormat::Markdown).unwrap();
+        assert_eq!(parsed.thread, transcript.thread);
+        assert_eq!(parsed.messages.len(), 2);
+        assert_eq!(parsed.messages[0].role, Role::User);
+        assert_eq!(
+            parsed.messages[0].content.text(),
+            "My invoice is wrong.\n\nCan you check?"
+        );
+        assert_eq!(parsed.messages[1].content.text(), "Fixed.");
+
+        let mut with_headings = transcript.clone();
+        with_headings.messages[1].content =
+            "Summary\n\n### Steps\n1. Refund\n\\### not a heading\n#### Notes".into();
+        let markdown = with_headings.render(ExportFormat::Markdown).unwrap();
+        assert!(markdown.contains("\n\\### Steps\n"));
+        let parsed = ThreadTranscript::parse(&markdown, ExportFormat::Markdown).unwrap();
+        assert_eq!(parsed.messages.len(), 2);
+        assert_eq!(
+            parsed.messages[1].content.text(),
+            with_headings.messages[1].content.text()
+        );
+
+        let mut indented = transcript.clone();
+        indented.thread.title = "Support\n- Thread ID: 99".to_string();
+        indented.messages[0].content = "\n    let x = 1;\n\nIndented above.".into();
+        let markdown = indented.render(ExportFormat::Markdown).unwrap();
+        assert!(markdown.starts_with("# Support - Thread ID: 99\n\n- Thread ID: 1\n"));
+        let parsed = ThreadTranscript::parse(&markdown, ExportFormat::Markdown).unwrap();
+        assert_eq!(parsed.thread.id, 1);
+        assert_eq!(
+            parsed.messages[0].content.text(),
+            indented.messages[0].content.text()
+        );
+        assert_eq!(parsed.messages[1].content.text(), "Fixed.");
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_thread() {
+        let mut server = Server::new_async().await;
+        let _thread = server
+            .mock("GET", "/threads/1")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "title": "Support", "created_at": 1672531200, "updated_at": 1672531200, "metadata": {"team": "billing"}}"#)
+            .create_async()
+            .await;
+        let _messages = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 10, "thread_id": 1, "role": "user", "content": "Hi", "created_at": 1672531200},
+                {"id": 11, "thread_id": 1, "role": "assistant", "content": "Hello", "created_at": 1672531210}
+            ], "total": 2}"#)
+            .create_async()
+            .await;
+        let create = server
+            .mock("POST", "/threads")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "title": "Support",
+                "metadata": {"team": "billing"},
+                "messages": [{"content": "Hi"}, {"content": "Hello"}]
+            })))
+            .with_status(200)
+            .with_body(r#"{"id": 7, "title": "Support", "created_at": 1672531300, "updated_at": 1672531300, "metadata": {"team": "billing"}}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("support.jsonl");
+        client
+            .export_thread_to_path(ThreadId(1), ExportFormat::Jsonl, &path)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        let imported = client
+            .import_thread_from_path(&path, ExportFormat::Jsonl)
+            .await
+            .unwrap();
+        assert_eq!(imported.id, 7);
+        create.assert_async().await;
+    }
+
+    // --- Local Store Tests ---
+
+    #[cfg(feature = "local-store")]
+    #[tokio::test]
+    async fn test_local_store_syncs_incrementally_and_serves_messages() {
+        let mut server = Server::new_async().await;
+        let threads_v1 = server
+            .mock("GET", "/threads?limit=50&offset=0")
+            .with_status(200)
+            .with_body(
+                r#"{"threads": [
+                {"id": 1, "title": "A", "created_at": 1672531200, "updated_at": 1672531200},
+                {"id": 2, "title": "B", "created_at": 1672531200, "updated_at": 1672531200250}
+            ], "total": 2}"#,
+            )
+            .create_async()
+            .await;
+        let messages1 = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 10, "thread_id": 1, "role": "user", "content": "Refund request", "created_at": 1672531200},
+                {"id": 11, "thread_id": 1, "role": "assistant", "content": "Refund issued", "created_at": 1672531210}
+            ], "total": 2}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let _messages2 = server
+            .mock("GET", "/threads/2/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [{"id": 20, "thread_id": 2, "role": "user", "content": "Hello", "created_at": 1672531200}], "total": 1}"#)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::open(dir.path().join("threads.db")).unwrap();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_local_store(store);
+
+        let report = client.sync_local_store().await.unwrap();
+        assert_eq!(report.updated, vec![1, 2]);
+
+        // Served from the store: the messages mock expects a single request
+        let page = client.get_thread_messages(ThreadId(1), 1, 1).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.messages[0].id, 11);
+        messages1.assert_async().await;
+
+        let hits = client
+            .local_store()
+            .unwrap()
+            .search_messages("refund")
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+
+        threads_v1.remove_async().await;
+        let _threads_v2 = server
+            .mock("GET", "/threads?limit=50&offset=0")
+            .with_status(200)
+            .with_body(
+                r#"{"threads": [
+                {"id": 2, "title": "B", "created_at": 1672531200, "updated_at": 1672531200250}
+            ], "total": 1}"#,
+            )
+            .create_async()
+            .await;
+
+        let report = client.sync_local_store().await.unwrap();
+        assert_eq!(
+            report,
+            LocalSyncReport {
+                updated: vec![],
+                unchanged: 1,
+                removed: vec![ThreadId(1)],
+            }
+        );
+        assert!(client
+            .local_store()
+            .unwrap()
+            .thread(ThreadId(1))
+            .unwrap()
+            .is_none());
+    }
+
+    #[cfg(feature = "local-store")]
+    #[tokio::test]
+    async fn test_local_store_invalidated_by_thread_writes() {
+        let mut server = Server::new_async().await;
+        let _delete = server
+            .mock("DELETE", "/threads/1/messages/10")
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let store = LocalStore::in_memory().unwrap();
+        let thread: Thread = serde_json::from_str(
+            r#"{"id": 1, "title": "A", "created_at": 1672531200, "updated_at": 1672531200}"#,
+        )
+        .unwrap();
+        store
+            .put_thread(&thread, &[message(10, Role::User, "secret")])
+            .unwrap();
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_local_store(store);
+
+        client
+            .delete_thread_message(ThreadId(1), MessageId(10))
+            .await
+            .unwrap();
+        assert!(client
+            .local_store()
+            .unwrap()
+            .thread(ThreadId(1))
+            .unwrap()
+            .is_none());
+    }
+
+    #[cfg(feature = "local-store")]
+    #[tokio::test]
+    async fn test_local_store_keeps_threads_across_runs() {
+        let mut server = Server::new_async().await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 11}"#)
+            .create_async()
+            .await;
+        let _status = server
+            .mock("GET", "/status/task-t1")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-t1", "status": "completed", "result": "Sure"}"#)
+            .create_async()
+            .await;
+        let messages = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 10, "thread_id": 1, "role": "user", "content": "Hi", "created_at": 1672531200},
+                {"id": 11, "thread_id": 1, "role": "user", "content": "Refund?", "created_at": 1672531300},
+                {"id": 12, "thread_id": 1, "role": "assistant", "content": "Sure", "created_at": 1672531310}
+            ], "total": 3}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let store = LocalStore::in_memory().unwrap();
+        let thread: Thread = serde_json::from_str(
+            r#"{"id": 1, "title": "A", "created_at": 1672531200, "updated_at": 1672531200}"#,
+        )
+        .unwrap();
+        store
+            .put_thread(&thread, &[message(10, Role::User, "Hi")])
+            .unwrap();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_local_store(store);
+
+        let opts = ThreadRunOptions {
+            prompt: "Refund?".to_string(),
+            ..Default::default()
+        };
+        let result = client
+            .run_in_thread_and_wait(ThreadId(1), opts, Some(Duration::from_millis(1)), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(result.message.id, 12);
+        // The reply was looked up on the server; the stored copy now has the whole turn
+        messages.assert_async().await;
+        let page = client
+            .get_thread_messages(ThreadId(1), 50, 0)
+            .await
+            .unwrap();
+        let ids: Vec<i64> = page.messages.iter().map(|m| m.id.0).collect();
+        assert_eq!(ids, vec![10, 11, 12]);
+        assert_eq!(page.messages[1].content.text(), "Refund?");
+        messages.assert_async().await;
+    }
+
+    #[cfg(feature = "local-store")]
+    #[tokio::test]
+    async fn test_local_store_serves_server_copy_after_run_in_thread() {
+        let mut server = Server::new_async().await;
+        let _run = server
+            .mock("POST", "/threads/1/runs")
+            .with_status(200)
+            .with_body(r#"{"task_id": "task-t1", "thread_id": 1, "message_id": 11}"#)
+            .create_async()
+            .await;
+        let messages = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [
+                {"id": 10, "thread_id": 1, "role": "user", "content": "Hi", "created_at": 1672531200},
+                {"id": 11, "thread_id": 1, "role": "user", "content": "Refund?", "created_at": 1672531300},
+                {"id": 12, "thread_id": 1, "role": "assistant", "content": "Sure", "created_at": 1672531310}
+            ], "total": 3}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let store = LocalStore::in_memory().unwrap();
+        let thread: Thread = serde_json::from_str(
+            r#"{"id": 1, "title": "A", "created_at": 1672531200, "updated_at": 1672531200}"#,
+        )
+        .unwrap();
+        store
+            .put_thread(&thread, &[message(10, Role::User, "Hi")])
+            .unwrap();
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_local_store(store);
+
+        let opts = ThreadRunOptions {
+            prompt: "Refund?".to_string(),
+            ..Default::default()
+        };
+        client.run_in_thread(ThreadId(1), opts).await.unwrap();
+        // Without the reply the stored copy is stale, so the server is asked instead
+        let page = client
+            .get_thread_messages(ThreadId(1), 50, 0)
+            .await
+            .unwrap();
+        let ids: Vec<i64> = page.messages.iter().map(|m| m.id.0).collect();
+        assert_eq!(ids, vec![10, 11, 12]);
+        messages.assert_async().await;
+    }
+
+    // --- Search Tests ---
+
+    #[test]
+    fn test_search_index_ranks_and_snippets() {
+        let mut index = SearchIndex::new();
+        index.add(&message(
+            1,
+            Role::User,
+            "My invoice total looks wrong this month.",
+        ));
+        index.add(&message(
+            2,
+            Role::Assistant,
+            "Invoice corrected. The invoice now shows the discounted invoice amount.",
+        ));
+        index.add(&message(
+            3,
+            Role::User,
+            "Thanks, unrelated question about exports.",
+        ));
+        assert_eq!(index.len(), 3);
+
+        let hits = index.search("INVOICE", 10);
+        let ids: Vec<i64> = hits.iter().map(|hit| hit.message_id.get()).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert!(hits[0].score > hits[1].score);
+        assert_eq!(hits[1].snippet, "My invoice total looks wrong this month.");
+        assert!(index.search("missing", 10).is_empty());
+
+        let long = format!("{} needle {}", "a ".repeat(100), "b ".repeat(100));
+        index.add(&message(4, Role::User, &long));
+        let snippet = &index.search("needle", 1)[0].snippet;
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("needle"));
+    }
+
+    #[tokio::test]
+    async fn test_search_threads_uses_server_endpoint() {
+        let mut server = Server::new_async().await;
+        let _search = server
+            .mock("GET", "/threads/search?q=refund+policy&limit=20")
+            .with_status(200)
+            .with_body(r#"{"hits": [{"thread_id": 1, "message_id": 10, "snippet": "our refund policy", "score": 3.5}]}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let hits = client.search_threads("refund policy").await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, 10);
+    }
+
+    #[tokio::test]
+    async fn test_search_threads_falls_back_to_local_index() {
+        let mut server = Server::new_async().await;
+        let search = server
+            .mock("GET", "/threads/search?q=refund&limit=20")
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+        let _threads = server
+            .mock("GET", "/threads?limit=50&offset=0")
+            .with_status(200)
+            .with_body(
+                r#"{"threads": [
+                {"id": 1, "title": "A", "created_at": 1672531200, "updated_at": 1672531200},
+                {"id": 2, "title": "B", "created_at": 1672531200, "updated_at": 1672531200}
+            ], "total": 2}"#,
+            )
+            .create_async()
+            .await;
+        let _messages1 = server
+            .mock("GET", "/threads/1/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [{"id": 10, "thread_id": 1, "role": "user", "content": "Hello there", "created_at": 1672531200}], "total": 1}"#)
+            .create_async()
+            .await;
+        let messages2 = server
+            .mock("GET", "/threads/2/messages?limit=50&offset=0")
+            .with_status(200)
+            .with_body(r#"{"messages": [{"id": 20, "thread_id": 2, "role": "user", "content": "I want a refund", "created_at": 1672531200}], "total": 1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let metrics = RecordingMetrics::default();
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_metrics(metrics.clone())
+        .with_middleware(RecordingMiddleware {
+            name: "m",
+            events: events.clone(),
+        });
+
+        let hits = client.search_threads("refund").await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            (hits[0].thread_id, hits[0].message_id),
+            (ThreadId(2), MessageId(20))
+        );
+        assert_eq!(hits[0].snippet, "I want a refund");
+        assert!(client.search_threads("  ").await.unwrap().is_empty());
+
+        // The index is reused, and the expected 404 probe is not reported as a failure
+        let hits = client.search_threads("hello").await.unwrap();
+        assert_eq!(hits[0].message_id, MessageId(10));
+        search.assert_async().await;
+        messages2.assert_async().await;
+        assert!(!metrics
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.starts_with("error")));
+        assert!(!events.lock().unwrap().iter().any(|e| e.contains("error")));
+
+        client.invalidate_search_index();
+        let client = client.with_search_index(SearchIndex::new());
+        assert!(client.search_threads("refund").await.unwrap().is_empty());
+        search.assert_async().await;
+    }
+
+    // --- ID Tests ---
+
+    #[test]
+    fn test_id_parsing_and_serde() {
+        let task_id: TaskId = "task-1".parse().unwrap();
+        assert_eq!(task_id.to_string(), "task-1");
+        assert!(matches!(
+            " ".parse::<TaskId>(),
+            Err(TaskForceAIError::EmptyTaskId)
+        ));
+        assert!(matches!(
+            "".parse::<FileId>(),
+            Err(TaskForceAIError::InvalidId(_))
+        ));
+
+        let thread_id: ThreadId = "42".parse().unwrap();
+        assert_eq!(thread_id, ThreadId(42));
+        assert!(matches!(
+            "abc".parse::<MessageId>(),
+            Err(TaskForceAIError::InvalidId(_))
+        ));
+
+        let status: TaskStatus =
+            serde_json::from_str(r#"{"taskId": "task-1", "status": "completed"}"#).unwrap();
+        assert_eq!(status.task_id, task_id);
+        assert_eq!(
+            serde_json::to_value(ThreadId(7)).unwrap(),
+            serde_json::json!(7)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ids_are_validated_and_percent_encoded_in_paths() {
+        let mut server = Server::new_async().await;
+        let file_mock = server
+            .mock("GET", "/files/reports%2F2024%20q1")
+            .with_status(200)
+            .with_body(r#"{"id": "reports/2024 q1", "filename": "q1.pdf", "purpose": "assistants", "bytes": 1, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let file = client
+            .get_file(&FileId::from("reports/2024 q1"))
+            .await
+            .unwrap();
+        assert_eq!(file.id, "reports/2024 q1");
+        file_mock.assert_async().await;
+
+        let err = client.get_thread(ThreadId(0)).await.unwrap_err();
+        assert!(matches!(err, TaskForceAIError::InvalidId(_)));
+        let err = client
+            .delete_thread_message(ThreadId(1), MessageId(-5))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskForceAIError::InvalidId(_)));
+        let err = client.get_file(&FileId::from("bad\nid")).await.unwrap_err();
+        assert!(matches!(err, TaskForceAIError::InvalidId(_)));
+        for dots in [".", ".."] {
+            let err = client.delete_file(&FileId::from(dots)).await.unwrap_err();
+            assert!(matches!(err, TaskForceAIError::InvalidId(_)), "{}", dots);
+            let err = client
+                .get_task_status(&TaskId::from(dots))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, TaskForceAIError::InvalidId(_)), "{}", dots);
+            let err = client.get_model(dots).await.unwrap_err();
+            assert!(matches!(err, TaskForceAIError::InvalidId(_)), "{}", dots);
+        }
+        assert!(FileId::new("..").is_err());
+        let file_mock = server
+            .mock("GET", "/files/..%2Fetc")
+            .with_status(200)
+            .with_body(r#"{"id": "../etc", "filename": "etc", "purpose": "assistants", "bytes": 1, "created_at": 1672531200}"#)
+            .create_async()
+            .await;
+        client.get_file(&FileId::from("../etc")).await.unwrap();
+        file_mock.assert_async().await;
+    }
+
+    // --- Model Catalog Tests ---
+
+    const MODELS_BODY: &str = r#"{"models": [
+        {"id": "gpt-4o", "contextWindow": 128000, "supportsVision": true, "supportsTools": true,
+         "pricing": {"inputPerMillion": 2.5, "outputPerMillion": 10.0}},
+        {"id": "text-mini", "context_window": 16000, "supports_tools": true, "status": "deprecated",
+         "replacement": "gpt-4o"},
+        {"id": "legacy-1", "contextWindow": 4096, "status": "retired", "replacement": "gpt-4o"}
+    ]}"#;
+
+    #[tokio::test]
+    async fn test_list_models_cached() {
+        let mut server = Server::new_async().await;
+        let catalog = server
+            .mock("GET", "/models")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .expect(2)
+            .create_async()
+            .await;
+        let fetched = server
+            .mock("GET", "/models/org%2Fcustom")
+            .with_status(200)
+            .with_body(r#"{"id": "org/custom", "contextWindow": 8192}"#)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let models = client.list_models().await.unwrap();
+        assert_eq!(models.len(), 3);
+        assert_eq!(models[0].context_window, 128000);
+        assert_eq!(models[0].pricing.unwrap().output_per_million, 10.0);
+        assert!(models[1].supports_tools && !models[1].supports_vision);
+        assert!(models[1].is_deprecated());
+        assert_eq!(models[2].status, ModelStatus::Retired);
+
+        // Served from the cache
+        let model = client.get_model("text-mini").await.unwrap();
+        assert_eq!(model.replacement.as_deref(), Some("gpt-4o"));
+        client.list_models().await.unwrap();
+
+        // Models missing from the catalog are fetched individually
+        let custom = client.get_model("org/custom").await.unwrap();
+        assert_eq!(custom.status, ModelStatus::Active);
+        assert!(client.get_model(" ").await.is_err());
+
+        client.invalidate_model_catalog();
+        client.list_models().await.unwrap();
+        catalog.assert_async().await;
+        fetched.assert_async().await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_model_catalog_ttl(Duration::ZERO);
+        let expired = server
+            .mock("GET", "/models")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .expect(2)
+            .create_async()
+            .await;
+        client.list_models().await.unwrap();
+        client.list_models().await.unwrap();
+        expired.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_submit_task_model_validation() {
+        let mut server = Server::new_async().await;
+        let catalog = server
+            .mock("GET", "/models")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .expect(1)
+            .create_async()
+            .await;
+        let run = server
+            .mock("POST", "/run")
+            .with_status(200)
+            .with_body(r#"{"taskId": "task-model"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = TaskForceAI::new(TaskForceAIOptions {
+            base_url: Some(server.url()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_model_validation(true);
+        let image = ImageAttachment::from_bytes(PNG_1X1, None).unwrap();
+        let opts = |model_id: &str, images: bool| TaskSubmissionOptions {
+            model_id: Some(model_id.to_string()),
+            images: images.then(|| vec![image.clone()]),
+            ..Default::default()
+        };
+
+        let err = client
+            .submit_task("hi", Some(opts("gpt-5-turbo", false)))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, TaskForceAIError::InvalidOptions(msg) if msg.contains("gpt-5-turbo"))
+        );
+        let err = client
+            .submit_task("hi", Some(opts("legacy-1", false)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskForceAIError::InvalidOptions(msg) if msg.contains("gpt-4o")));
+        let err = client
+            .submit_task("hi", Some(opts("text-mini", true)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskForceAIError::InvalidAttachment(_)));
+
+        client
+            .submit_task("hi", Some(opts("text-mini", false)))
+            .await
+            .unwrap();
+        client
+            .submit_task("describe", Some(opts("gpt-4o", true)))
+            .await
+            .unwrap();
+        catalog.assert_async().await;
+        run.assert_async().await;
+    }
 }
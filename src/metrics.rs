@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+/// Receives measurements about how the SDK behaves at runtime.
+///
+/// Register a recorder with [`TaskForceAI::with_metrics`](crate::TaskForceAI::with_metrics)
+/// to forward these to the `metrics` crate, Prometheus or any other backend. Every method
+/// has a no-op default, so implementors only override what they need.
+pub trait MetricsRecorder: Send + Sync {
+    /// Latency of a single HTTP request. `endpoint` is the path with IDs replaced by `{id}`,
+    /// e.g. `/status/{id}`. `status` is `None` when no response was received.
+    fn record_request(
+        &self,
+        _method: &reqwest::Method,
+        _endpoint: &str,
+        _status: Option<u16>,
+        _latency: Duration,
+    ) {
+    }
+
+    /// Time from task submission until the task completed.
+    fn record_task_duration(&self, _duration: Duration) {}
+
+    /// Number of status polls `wait_for_completion` made for one task.
+    fn record_poll_attempts(&self, _attempts: u32) {}
+
+    /// Bytes sent in a file upload.
+    fn record_bytes_uploaded(&self, _bytes: u64) {}
+
+    /// Bytes received in a file download.
+    fn record_bytes_downloaded(&self, _bytes: u64) {}
+
+    /// A status stream reconnected after its connection dropped. Streams do not reconnect
+    /// yet, so this is not called today.
+    fn record_stream_reconnect(&self) {}
+
+    /// An error surfaced by the SDK, labelled with its [`TaskForceAIError::kind`](crate::TaskForceAIError::kind).
+    fn record_error(&self, _kind: &'static str) {}
+}
+
+/// The recorder used when none is configured.
+pub(crate) struct NoopMetrics;
+
+impl MetricsRecorder for NoopMetrics {}
+
+/// Path segments that are followed by a resource ID.
+const COLLECTIONS: &[&str] = &[
+    "status", "stream", "files", "threads", "messages", "uploads", "parts",
+];
+
+/// Normalizes a request path into a low-cardinality endpoint label.
+pub(crate) fn endpoint_label(path: &str) -> String {
+    let mut label = String::new();
+    let mut previous = "";
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        label.push('/');
+        if COLLECTIONS.contains(&previous) {
+            label.push_str("{id}");
+        } else {
+            label.push_str(segment);
+        }
+        previous = segment;
+    }
+    if label.is_empty() {
+        label.push('/');
+    }
+    label
+}
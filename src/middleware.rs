@@ -0,0 +1,55 @@
+use crate::error::TaskForceAIError;
+use async_trait::async_trait;
+use std::time::{Duration, Instant};
+
+/// Describes an outgoing request as it was sent, after every `before_request` hook ran.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub method: reqwest::Method,
+    pub url: reqwest::Url,
+    pub started_at: Instant,
+}
+
+impl RequestContext {
+    pub(crate) fn new(request: &reqwest::Request) -> Self {
+        Self {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Time elapsed since the request was handed to the HTTP client.
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+}
+
+/// A hook into every HTTP request made by the client.
+///
+/// Middleware is registered with [`TaskForceAI::with_middleware`](crate::TaskForceAI::with_middleware).
+/// `before_request` runs in registration order; `after_response` and `on_error` run in
+/// reverse order, so the first middleware registered wraps all the others.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before a request is sent. The request may be modified (headers, URL, body).
+    /// Returning an error aborts the request.
+    async fn before_request(
+        &self,
+        _request: &mut reqwest::Request,
+    ) -> Result<(), TaskForceAIError> {
+        Ok(())
+    }
+
+    /// Called once a response has been received, including non-success responses.
+    async fn after_response(
+        &self,
+        _context: &RequestContext,
+        _response: &reqwest::Response,
+    ) -> Result<(), TaskForceAIError> {
+        Ok(())
+    }
+
+    /// Called when the request fails, whether in a hook, on the network, or with an API error.
+    async fn on_error(&self, _context: &RequestContext, _error: &TaskForceAIError) {}
+}
@@ -0,0 +1,43 @@
+//! Content type detection for uploads and attachments.
+
+/// Signatures checked against the first bytes of a file, most specific first.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+];
+
+/// Number of leading bytes needed to detect any supported signature.
+pub(crate) const SNIFF_LEN: usize = 512;
+
+/// Sniffed types that are only containers for formats such as DOCX, ODT, EPUB or JAR.
+const CONTAINER_TYPES: &[&str] = &["application/zip", "application/gzip"];
+
+/// Detects a MIME type from leading bytes, falling back to the filename extension.
+/// When the bytes only reveal a generic container, the extension's more specific type wins.
+pub(crate) fn detect(filename: &str, head: &[u8]) -> String {
+    let by_extension = mime_guess::from_path(filename)
+        .first_raw()
+        .filter(|mime| *mime != "application/octet-stream");
+    match sniff(head) {
+        Some(sniffed) if CONTAINER_TYPES.contains(&sniffed) => by_extension.unwrap_or(sniffed),
+        Some(sniffed) => sniffed,
+        None => by_extension.unwrap_or("application/octet-stream"),
+    }
+    .to_string()
+}
+
+/// Detects a MIME type from magic bytes only.
+pub(crate) fn sniff(head: &[u8]) -> Option<&'static str> {
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| head.starts_with(signature))
+        .map(|(_, mime)| *mime)
+}
@@ -0,0 +1,231 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::ids::encode_path_segment;
+use crate::types::TaskSubmissionOptions;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a fetched model catalog is reused before it is fetched again.
+pub const DEFAULT_MODEL_CATALOG_TTL: Duration = Duration::from_secs(300);
+
+/// Lifecycle stage of a model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelStatus {
+    #[default]
+    Active,
+    /// Still accepted, but scheduled for removal
+    Deprecated,
+    /// No longer accepted
+    Retired,
+}
+
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPricing {
+    #[serde(default, alias = "input_per_million")]
+    pub input_per_million: f64,
+    #[serde(default, alias = "output_per_million")]
+    pub output_per_million: f64,
+}
+
+/// A model that can be selected with [`TaskSubmissionOptions::model_id`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Maximum tokens of input and output combined
+    #[serde(default, alias = "context_window")]
+    pub context_window: u64,
+    /// Whether the model accepts image attachments
+    #[serde(default, alias = "supports_vision")]
+    pub supports_vision: bool,
+    /// Whether the model can call tools
+    #[serde(default, alias = "supports_tools")]
+    pub supports_tools: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<ModelPricing>,
+    #[serde(default)]
+    pub status: ModelStatus,
+    /// Suggested successor of a deprecated or retired model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+impl Model {
+    pub fn is_deprecated(&self) -> bool {
+        self.status != ModelStatus::Active
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    models: Vec<Model>,
+}
+
+/// The client's cached copy of the model catalog.
+pub(crate) struct ModelCatalog {
+    state: Mutex<CatalogState>,
+}
+
+struct CatalogState {
+    ttl: Duration,
+    validate: bool,
+    models: Option<(Instant, Arc<Vec<Model>>)>,
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(CatalogState {
+                ttl: DEFAULT_MODEL_CATALOG_TTL,
+                validate: false,
+                models: None,
+            }),
+        }
+    }
+}
+
+impl ModelCatalog {
+    fn state(&self) -> std::sync::MutexGuard<'_, CatalogState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn fresh(&self) -> Option<Arc<Vec<Model>>> {
+        let state = self.state();
+        state
+            .models
+            .as_ref()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < state.ttl)
+            .map(|(_, models)| models.clone())
+    }
+
+    fn store(&self, models: Vec<Model>) -> Arc<Vec<Model>> {
+        let models = Arc::new(models);
+        self.state().models = Some((Instant::now(), models.clone()));
+        models
+    }
+}
+
+impl TaskForceAI {
+    /// Sets how long the model catalog is cached. A zero TTL fetches it on every use.
+    pub fn with_model_catalog_ttl(self, ttl: Duration) -> Self {
+        self.models.state().ttl = ttl;
+        self
+    }
+
+    /// Checks `model_id` against the model catalog in `submit_task`, rejecting unknown or
+    /// retired models and image attachments sent to models without vision support.
+    pub fn with_model_validation(self, enabled: bool) -> Self {
+        self.models.state().validate = enabled;
+        self
+    }
+
+    /// Drops the cached model catalog so the next lookup fetches it again.
+    pub fn invalidate_model_catalog(&self) {
+        self.models.state().models = None;
+    }
+
+    /// Lists the models available to the account, served from cache while it is fresh.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.list_models",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn list_models(&self) -> Result<Vec<Model>, TaskForceAIError> {
+        Ok(self.model_catalog().await?.as_ref().clone())
+    }
+
+    /// Looks up one model, consulting the cached catalog before the server.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.get_model",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                model_id = %model_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn get_model(&self, model_id: &str) -> Result<Model, TaskForceAIError> {
+        if model_id.trim().is_empty() {
+            return Err(self.error(TaskForceAIError::InvalidId(
+                "Model ID must be a non-empty string".to_string(),
+            )));
+        }
+        let cached = self
+            .models
+            .fresh()
+            .and_then(|models| models.iter().find(|model| model.id == model_id).cloned());
+        if let Some(model) = cached {
+            return Ok(model);
+        }
+        let segment = encode_path_segment(model_id, "Model ID").map_err(|e| self.error(e))?;
+        self.request(reqwest::Method::GET, &format!("/models/{}", segment), None)
+            .await
+    }
+
+    async fn model_catalog(&self) -> Result<Arc<Vec<Model>>, TaskForceAIError> {
+        if let Some(models) = self.models.fresh() {
+            return Ok(models);
+        }
+        let response: ModelsResponse = self.request(reqwest::Method::GET, "/models", None).await?;
+        Ok(self.models.store(response.models))
+    }
+
+    /// Checks task options against the model catalog when model validation is enabled.
+    pub(crate) async fn validate_model(
+        &self,
+        options: &TaskSubmissionOptions,
+    ) -> Result<(), TaskForceAIError> {
+        if !self.models.state().validate {
+            return Ok(());
+        }
+        let Some(model_id) = options.model_id.as_deref() else {
+            return Ok(());
+        };
+
+        let catalog = self.model_catalog().await?;
+        let model = catalog
+            .iter()
+            .find(|model| model.id == model_id)
+            .ok_or_else(|| {
+                self.error(TaskForceAIError::InvalidOptions(format!(
+                    "unknown model_id \"{}\"",
+                    model_id
+                )))
+            })?;
+        if model.status == ModelStatus::Retired {
+            let hint = model
+                .replacement
+                .as_ref()
+                .map(|replacement| format!("; use \"{}\" instead", replacement))
+                .unwrap_or_default();
+            return Err(self.error(TaskForceAIError::InvalidOptions(format!(
+                "model \"{}\" is retired{}",
+                model_id, hint
+            ))));
+        }
+        if !model.supports_vision && options.images.as_ref().is_some_and(|i| !i.is_empty()) {
+            return Err(self.error(TaskForceAIError::InvalidAttachment(format!(
+                "model \"{}\" does not accept images",
+                model_id
+            ))));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::files::{File, FileListResponse, ListFilesQuery};
+use crate::ids::ThreadId;
+use crate::threads::{Thread, ThreadListResponse, ThreadMessage, ThreadMessagesResponse};
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, Stream};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub const DEFAULT_PAGE_SIZE: i32 = 50;
+
+/// One page of results from a list endpoint.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    /// Opaque cursor for the next page, when the server paginates by cursor
+    pub next_cursor: Option<String>,
+}
+
+/// The position of the page to fetch next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    pub limit: i32,
+    pub offset: i32,
+    pub cursor: Option<String>,
+}
+
+impl PageRequest {
+    /// Encodes the request as URL query parameters. A cursor replaces the offset.
+    pub fn query(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("limit", &self.limit.to_string());
+        match &self.cursor {
+            Some(cursor) => query.append_pair("cursor", cursor),
+            None => query.append_pair("offset", &self.offset.to_string()),
+        };
+        query.finish()
+    }
+}
+
+type FetchPage<'a, T> =
+    Box<dyn FnMut(PageRequest) -> BoxFuture<'a, Result<Page<T>, TaskForceAIError>> + Send + 'a>;
+
+/// A stream over every item of a paginated list, fetching pages lazily.
+///
+/// Pagination stops once `total` items have been read, or when the server returns
+/// an empty page. If the server returns a `next_cursor`, it is followed instead of
+/// the offset.
+pub struct Paginator<'a, T> {
+    fetch: FetchPage<'a, T>,
+    page_size: i32,
+    offset: i32,
+    cursor: Option<String>,
+    buffer: VecDeque<T>,
+    pending: Option<BoxFuture<'a, Result<Page<T>, TaskForceAIError>>>,
+    done: bool,
+}
+
+impl<'a, T> Paginator<'a, T> {
+    pub fn new(
+        fetch: impl FnMut(PageRequest) -> BoxFuture<'a, Result<Page<T>, TaskForceAIError>> + Send + 'a,
+    ) -> Self {
+        Self {
+            fetch: Box::new(fetch),
+            page_size: DEFAULT_PAGE_SIZE,
+            offset: 0,
+            cursor: None,
+            buffer: VecDeque::new(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Sets how many items are requested per page.
+    pub fn with_page_size(mut self, page_size: i32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+}
+
+impl<T> Unpin for Paginator<'_, T> {}
+
+impl<T> Stream for Paginator<'_, T> {
+    type Item = Result<T, TaskForceAIError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(item) = self.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            if self.pending.is_none() {
+                let request = PageRequest {
+                    limit: self.page_size,
+                    offset: self.offset,
+                    cursor: self.cursor.clone(),
+                };
+                let future = (self.fetch)(request);
+                self.pending = Some(future);
+            }
+
+            let page = match self.pending.as_mut().unwrap().poll_unpin(cx) {
+                Poll::Ready(page) => page,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.pending = None;
+
+            match page {
+                Ok(page) => {
+                    self.offset += page.items.len() as i32;
+                    self.done = page.items.is_empty()
+                        || match &page.next_cursor {
+                            Some(_) => false,
+                            None => i64::from(self.offset) >= page.total,
+                        };
+                    self.cursor = page.next_cursor;
+                    self.buffer.extend(page.items);
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+impl TaskForceAI {
+    /// Streams every uploaded file, fetching pages as needed.
+    pub fn files(&self) -> Paginator<'_, File> {
+        self.files_matching(ListFilesQuery::new())
+    }
+
+    /// Streams every uploaded file matching `query`, fetching pages as needed.
+    /// The query's own limit, offset and cursor are replaced by the paginator's.
+    pub fn files_matching(&self, query: ListFilesQuery) -> Paginator<'_, File> {
+        Paginator::new(move |page: PageRequest| {
+            let query = ListFilesQuery {
+                limit: Some(page.limit),
+                offset: page.cursor.is_none().then_some(page.offset),
+                cursor: page.cursor,
+                ..query.clone()
+            };
+            async move {
+                let response: FileListResponse = self.list_files_with_query(&query).await?;
+                Ok(Page {
+                    items: response.files,
+                    total: response.total,
+                    next_cursor: response.next_cursor,
+                })
+            }
+            .boxed()
+        })
+    }
+
+    /// Streams every thread, fetching pages as needed.
+    pub fn threads(&self) -> Paginator<'_, Thread> {
+        Paginator::new(move |page: PageRequest| {
+            async move {
+                let path = format!("/threads?{}", page.query());
+                let response: ThreadListResponse =
+                    self.request(reqwest::Method::GET, &path, None).await?;
+                Ok(Page {
+                    items: response.threads,
+                    total: response.total,
+                    next_cursor: response.next_cursor,
+                })
+            }
+            .boxed()
+        })
+    }
+
+    /// Streams every message of a thread, fetching pages as needed.
+    pub fn thread_messages(&self, thread_id: ThreadId) -> Paginator<'_, ThreadMessage> {
+        Paginator::new(move |page: PageRequest| {
+            async move {
+                if let Some(response) = self.cached_thread_messages(thread_id, &page).await {
+                    return Ok(Page {
+                        items: response.messages,
+                        total: response.total,
+                        next_cursor: response.next_cursor,
+                    });
+                }
+                let path = format!(
+                    "/threads/{}/messages?{}",
+                    self.thread_segment(thread_id)?,
+                    page.query()
+                );
+                let response: ThreadMessagesResponse =
+                    self.request(reqwest::Method::GET, &path, None).await?;
+                Ok(Page {
+                    items: response.messages,
+                    total: response.total,
+                    next_cursor: response.next_cursor,
+                })
+            }
+            .boxed()
+        })
+    }
+}
@@ -0,0 +1,283 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::ids::{MessageId, ThreadId};
+use crate::threads::ThreadMessage;
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Maximum number of hits returned by [`TaskForceAI::search_threads`].
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// How long a locally built search index is reused before it is rebuilt.
+pub const DEFAULT_SEARCH_INDEX_TTL: Duration = Duration::from_secs(300);
+
+/// Statuses with which a server without a search endpoint answers the probe.
+const SEARCH_UNSUPPORTED: &[u16] = &[404, 405, 501];
+
+/// Characters of context kept on each side of the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 60;
+
+/// A message matching a search query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub thread_id: ThreadId,
+    pub message_id: MessageId,
+    /// An excerpt of the message around the first match
+    pub snippet: String,
+    /// Relevance; higher is better. Only comparable within one result set.
+    pub score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    hits: Vec<SearchHit>,
+}
+
+#[derive(Debug, Clone)]
+struct Document {
+    thread_id: ThreadId,
+    message_id: MessageId,
+    text: String,
+    terms: usize,
+}
+
+/// An in-memory inverted index over thread messages, ranked by TF-IDF.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    documents: Vec<Document>,
+    /// Term -> (document index, occurrences)
+    postings: HashMap<String, Vec<(usize, usize)>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a message's text to the index.
+    pub fn add(&mut self, message: &ThreadMessage) {
+        let text = message.content.text();
+        let tokens = tokenize(&text);
+        let document = self.documents.len();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for token in &tokens {
+            *counts.entry(token.clone()).or_default() += 1;
+        }
+        for (term, count) in counts {
+            self.postings
+                .entry(term)
+                .or_default()
+                .push((document, count));
+        }
+        self.documents.push(Document {
+            thread_id: message.thread_id,
+            message_id: message.id,
+            text,
+            terms: tokens.len(),
+        });
+    }
+
+    /// Number of indexed messages.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Returns up to `limit` messages containing any query term, best match first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let terms = tokenize(query);
+        let total = self.documents.len() as f64;
+
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+            let idf = (1.0 + total / postings.len() as f64).ln();
+            for &(document, count) in postings {
+                let tf = count as f64 / self.documents[document].terms as f64;
+                *scores.entry(document).or_default() += tf * idf;
+            }
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(document, score)| {
+                let document = &self.documents[document];
+                SearchHit {
+                    thread_id: document.thread_id,
+                    message_id: document.message_id,
+                    snippet: snippet(&document.text, &terms),
+                    score,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The client's local search index, used when the server has no search endpoint.
+pub(crate) struct SearchCache {
+    state: Mutex<SearchCacheState>,
+}
+
+struct SearchCacheState {
+    ttl: Duration,
+    index: Option<(Instant, Arc<SearchIndex>)>,
+}
+
+impl Default for SearchCache {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(SearchCacheState {
+                ttl: DEFAULT_SEARCH_INDEX_TTL,
+                index: None,
+            }),
+        }
+    }
+}
+
+impl SearchCache {
+    fn state(&self) -> std::sync::MutexGuard<'_, SearchCacheState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn fresh(&self) -> Option<Arc<SearchIndex>> {
+        let state = self.state();
+        state
+            .index
+            .as_ref()
+            .filter(|(built_at, _)| built_at.elapsed() < state.ttl)
+            .map(|(_, index)| index.clone())
+    }
+
+    fn store(&self, index: SearchIndex) -> Arc<SearchIndex> {
+        let index = Arc::new(index);
+        self.state().index = Some((Instant::now(), index.clone()));
+        index
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Cuts an excerpt of `text` around the first occurrence of any of `terms`.
+fn snippet(text: &str, terms: &[String]) -> String {
+    let lower = text.to_lowercase();
+    let chars: Vec<char> = text.chars().collect();
+    let start = terms
+        .iter()
+        .filter_map(|term| lower.find(term.as_str()))
+        .min()
+        .map(|byte| lower[..byte].chars().count())
+        .unwrap_or(0)
+        .min(chars.len());
+
+    let from = start.saturating_sub(SNIPPET_CONTEXT);
+    let to = (start + SNIPPET_CONTEXT).min(chars.len());
+    let mut excerpt: String = chars[from..to].iter().collect();
+    excerpt = excerpt.split_whitespace().collect::<Vec<_>>().join(" ");
+    if from > 0 {
+        excerpt.insert(0, '…');
+    }
+    if to < chars.len() {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+impl TaskForceAI {
+    /// Sets how long a locally built search index is reused before it is rebuilt.
+    pub fn with_search_index_ttl(self, ttl: Duration) -> Self {
+        self.search_index.state().ttl = ttl;
+        self
+    }
+
+    /// Searches with a prebuilt index instead of the server until the index's TTL runs out.
+    pub fn with_search_index(self, index: SearchIndex) -> Self {
+        self.search_index.store(index);
+        self
+    }
+
+    /// Drops the cached search index so the next search asks the server again.
+    pub fn invalidate_search_index(&self) {
+        self.search_index.state().index = None;
+    }
+
+    /// Searches thread messages by content and returns ranked hits.
+    ///
+    /// Uses the server's search endpoint; if the server does not offer one, every thread's
+    /// messages are fetched into a local [`SearchIndex`], which is cached on the client
+    /// for [`DEFAULT_SEARCH_INDEX_TTL`] (see [`with_search_index_ttl`](Self::with_search_index_ttl)).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.search_threads",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn search_threads(&self, query: &str) -> Result<Vec<SearchHit>, TaskForceAIError> {
+        if tokenize(query).is_empty() {
+            return Ok(Vec::new());
+        }
+        if let Some(index) = self.search_index.fresh() {
+            return Ok(index.search(query, DEFAULT_SEARCH_LIMIT));
+        }
+
+        let mut params = url::form_urlencoded::Serializer::new(String::new());
+        params.append_pair("q", query);
+        params.append_pair("limit", &DEFAULT_SEARCH_LIMIT.to_string());
+        let path = format!("/threads/search?{}", params.finish());
+        match self
+            .request_expecting::<SearchResponse>(
+                reqwest::Method::GET,
+                &path,
+                None,
+                SEARCH_UNSUPPORTED,
+            )
+            .await
+        {
+            Ok(response) => Ok(response.hits),
+            Err(TaskForceAIError::Api { status, .. })
+                if SEARCH_UNSUPPORTED.contains(&status.as_u16()) =>
+            {
+                let index = self.search_index.store(self.build_search_index().await?);
+                Ok(index.search(query, DEFAULT_SEARCH_LIMIT))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Indexes the messages of every thread. With a local store attached, stored threads
+    /// are read from disk.
+    pub async fn build_search_index(&self) -> Result<SearchIndex, TaskForceAIError> {
+        let mut index = SearchIndex::new();
+        let mut threads = self.threads();
+        while let Some(thread) = threads.try_next().await? {
+            let mut messages = self.thread_messages(thread.id);
+            while let Some(message) = messages.try_next().await? {
+                index.add(&message);
+            }
+        }
+        Ok(index)
+    }
+}
@@ -0,0 +1,331 @@
+//! An embedded SQLite mirror of threads and messages, enabled by the `local-store` feature.
+
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::ids::ThreadId;
+use crate::threads::{Thread, ThreadMessage};
+use futures_util::TryStreamExt;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS threads (
+        id INTEGER PRIMARY KEY,
+        updated_at INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        id INTEGER NOT NULL,
+        thread_id INTEGER NOT NULL REFERENCES threads(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        text TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (thread_id, id)
+    );
+    CREATE INDEX IF NOT EXISTS messages_by_position ON messages(thread_id, position);
+";
+
+impl From<rusqlite::Error> for TaskForceAIError {
+    fn from(e: rusqlite::Error) -> Self {
+        TaskForceAIError::Store(e.to_string())
+    }
+}
+
+/// An on-disk copy of threads and their messages for offline browsing and search.
+///
+/// A thread is stored together with all of its messages, so a stored thread is always
+/// complete. Attach a store with [`TaskForceAI::with_local_store`] to serve repeated
+/// `get_thread_messages` calls from disk and keep it current with
+/// [`TaskForceAI::sync_local_store`]. The client runs store calls on Tokio's blocking
+/// pool; the methods here block, so wrap direct calls from async code in `spawn_blocking`.
+#[derive(Clone)]
+pub struct LocalStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl std::fmt::Debug for LocalStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalStore").finish_non_exhaustive()
+    }
+}
+
+/// What changed in the local store during a sync.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalSyncReport {
+    /// Threads whose messages were (re)fetched because they were new or had changed
+    pub updated: Vec<ThreadId>,
+    /// Number of threads that were already up to date
+    pub unchanged: usize,
+    /// Threads removed locally because they no longer exist on the server
+    pub removed: Vec<ThreadId>,
+}
+
+impl LocalStore {
+    /// Opens (or creates) a store at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, TaskForceAIError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Creates a store that lives only in memory.
+    pub fn in_memory() -> Result<Self, TaskForceAIError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, TaskForceAIError> {
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs store calls on Tokio's blocking pool so they do not stall async workers.
+    pub(crate) async fn run_blocking<T, F>(&self, f: F) -> Result<T, TaskForceAIError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&LocalStore) -> Result<T, TaskForceAIError> + Send + 'static,
+    {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || f(&store))
+            .await
+            .map_err(|e| TaskForceAIError::Store(e.to_string()))?
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Every stored thread, most recently updated first.
+    pub fn threads(&self) -> Result<Vec<Thread>, TaskForceAIError> {
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT data FROM threads ORDER BY updated_at DESC, id DESC")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+    }
+
+    /// A stored thread, if present.
+    pub fn thread(&self, thread_id: ThreadId) -> Result<Option<Thread>, TaskForceAIError> {
+        let data: Option<String> = self
+            .connection()
+            .query_row(
+                "SELECT data FROM threads WHERE id = ?1",
+                [thread_id.0],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+    }
+
+    /// A page of a stored thread's messages in thread order, with the thread's total
+    /// message count. Returns `None` if the thread is not stored.
+    pub fn messages(
+        &self,
+        thread_id: ThreadId,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Option<(Vec<ThreadMessage>, i64)>, TaskForceAIError> {
+        if self.thread(thread_id)?.is_none() {
+            return Ok(None);
+        }
+        let connection = self.connection();
+        let total: i64 = connection.query_row(
+            "SELECT COUNT(*) FROM messages WHERE thread_id = ?1",
+            [thread_id.0],
+            |row| row.get(0),
+        )?;
+        let mut statement = connection.prepare(
+            "SELECT data FROM messages WHERE thread_id = ?1 ORDER BY position LIMIT ?2 OFFSET ?3",
+        )?;
+        let rows = statement.query_map(params![thread_id.0, limit, offset], |row| {
+            row.get::<_, String>(0)
+        })?;
+        let messages = rows
+            .map(|data| Ok(serde_json::from_str(&data?)?))
+            .collect::<Result<_, TaskForceAIError>>()?;
+        Ok(Some((messages, total)))
+    }
+
+    /// Stores a thread and replaces its messages.
+    pub fn put_thread(
+        &self,
+        thread: &Thread,
+        messages: &[ThreadMessage],
+    ) -> Result<(), TaskForceAIError> {
+        let mut connection = self.connection();
+        let tx = connection.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO threads (id, updated_at, data) VALUES (?1, ?2, ?3)",
+            params![
+                thread.id.0,
+                thread.updated_at.timestamp(),
+                serde_json::to_string(thread)?
+            ],
+        )?;
+        tx.execute("DELETE FROM messages WHERE thread_id = ?1", [thread.id.0])?;
+        for (position, message) in messages.iter().enumerate() {
+            tx.execute(
+                "INSERT OR REPLACE INTO messages (id, thread_id, position, text, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    message.id.0,
+                    thread.id.0,
+                    position as i64,
+                    message.content.text(),
+                    serde_json::to_string(message)?
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Adds messages to the end of a stored thread, replacing any with the same ID.
+    /// Returns `false` if the thread is not stored.
+    pub fn append_messages(
+        &self,
+        thread_id: ThreadId,
+        messages: &[ThreadMessage],
+    ) -> Result<bool, TaskForceAIError> {
+        let mut connection = self.connection();
+        let tx = connection.transaction()?;
+        let stored: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM threads WHERE id = ?1",
+                [thread_id.0],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if stored.is_none() {
+            return Ok(false);
+        }
+        for message in messages {
+            tx.execute(
+                "DELETE FROM messages WHERE thread_id = ?1 AND id = ?2",
+                [thread_id.0, message.id.0],
+            )?;
+            tx.execute(
+                "INSERT INTO messages (id, thread_id, position, text, data)
+                 SELECT ?1, ?2, COALESCE(MAX(position), -1) + 1, ?3, ?4
+                 FROM messages WHERE thread_id = ?2",
+                params![
+                    message.id.0,
+                    thread_id.0,
+                    message.content.text(),
+                    serde_json::to_string(message)?
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Removes a thread and its messages.
+    pub fn remove_thread(&self, thread_id: ThreadId) -> Result<(), TaskForceAIError> {
+        self.connection()
+            .execute("DELETE FROM threads WHERE id = ?1", [thread_id.0])?;
+        Ok(())
+    }
+
+    /// Stored messages whose text contains `query`, ignoring ASCII case, newest first.
+    pub fn search_messages(&self, query: &str) -> Result<Vec<ThreadMessage>, TaskForceAIError> {
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT m.data FROM messages m JOIN threads t ON t.id = m.thread_id
+             WHERE m.text LIKE ?1 ESCAPE '\\'
+             ORDER BY t.updated_at DESC, m.position DESC",
+        )?;
+        let rows = statement.query_map([pattern], |row| row.get::<_, String>(0))?;
+        rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+    }
+}
+
+impl TaskForceAI {
+    /// Mirrors threads and messages into `store`. Stored threads serve
+    /// `get_thread_messages` without a network round-trip.
+    pub fn with_local_store(mut self, store: LocalStore) -> Self {
+        self.local_store = Some(store);
+        self
+    }
+
+    /// The attached local store, if any.
+    pub fn local_store(&self) -> Option<&LocalStore> {
+        self.local_store.as_ref()
+    }
+
+    /// Brings the local store up to date. Only threads that are new or whose `updated_at`
+    /// changed have their messages fetched; threads deleted on the server are removed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.sync_local_store",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn sync_local_store(&self) -> Result<LocalSyncReport, TaskForceAIError> {
+        let store = self.local_store.as_ref().ok_or_else(|| {
+            self.error(TaskForceAIError::Store(
+                "No local store is attached to the client".to_string(),
+            ))
+        })?;
+
+        let mut report = LocalSyncReport::default();
+        let mut seen = HashSet::new();
+        let remote: Vec<Thread> = self.threads().try_collect().await?;
+        for thread in remote {
+            let thread_id = thread.id;
+            seen.insert(thread_id);
+            let stored = store
+                .run_blocking(move |store| store.thread(thread_id))
+                .await
+                .map_err(|e| self.error(e))?;
+            if stored.is_some_and(|stored| stored.updated_at == thread.updated_at) {
+                report.unchanged += 1;
+                continue;
+            }
+            // Drop the stale copy first so the messages below come from the server
+            store
+                .run_blocking(move |store| store.remove_thread(thread_id))
+                .await
+                .map_err(|e| self.error(e))?;
+            let messages: Vec<ThreadMessage> =
+                self.thread_messages(thread_id).try_collect().await?;
+            store
+                .run_blocking(move |store| store.put_thread(&thread, &messages))
+                .await
+                .map_err(|e| self.error(e))?;
+            report.updated.push(thread_id);
+        }
+
+        let stored = store
+            .run_blocking(|store| store.threads())
+            .await
+            .map_err(|e| self.error(e))?;
+        for thread in stored {
+            if !seen.contains(&thread.id) {
+                let thread_id = thread.id;
+                store
+                    .run_blocking(move |store| store.remove_thread(thread_id))
+                    .await
+                    .map_err(|e| self.error(e))?;
+                report.removed.push(thread_id);
+            }
+        }
+        Ok(report)
+    }
+}
@@ -1,5 +1,8 @@
 use crate::client::TaskForceAI;
 use crate::error::TaskForceAIError;
+use crate::ids::{TaskId, ThreadId};
+use crate::telemetry;
+use crate::threads::ThreadRunOptions;
 use crate::types::{TaskStatus, TaskSubmissionOptions};
 use futures_util::{Stream, StreamExt};
 use std::pin::Pin;
@@ -8,13 +11,23 @@ pub type TaskStatusStream =
     Pin<Box<dyn Stream<Item = Result<TaskStatus, TaskForceAIError>> + Send>>;
 
 impl TaskForceAI {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.stream_task_status",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                task_id = %task_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn stream_task_status(
         &self,
-        task_id: &str,
+        task_id: &TaskId,
     ) -> Result<TaskStatusStream, TaskForceAIError> {
-        if task_id.trim().is_empty() {
-            return Err(TaskForceAIError::EmptyTaskId);
-        }
+        let segment = task_id.path_segment().map_err(|e| self.error(e))?;
 
         if self.mock_mode {
             let status = self.get_task_status(task_id).await?;
@@ -22,24 +35,15 @@ impl TaskForceAI {
             return Ok(Box::pin(stream));
         }
 
-        let url = format!("{}/stream/{}", self.base_url, task_id);
-        let mut request = self.client.get(&url);
-
-        if !self.api_key.is_empty() {
-            request = request.header("x-api-key", &self.api_key);
-        }
-
-        request = request.header("Accept", "text/event-stream");
+        let url = format!("{}/stream/{}", self.base_url, segment);
+        let request = self.client.get(&url).header("Accept", "text/event-stream");
 
-        let response = request.send().await?;
-        if !response.status().is_success() {
-            let status = response.status();
-            let message = response.text().await.unwrap_or_default();
-            return Err(TaskForceAIError::Api { status, message });
-        }
+        let response = self.send(request).await?;
 
         let mut bytes_stream = response.bytes_stream();
         let mut buffer = String::new();
+        let metrics = self.metrics.clone();
+        let usage = self.usage.clone();
 
         let s = futures_util::stream::poll_fn(move |cx| {
             loop {
@@ -50,11 +54,15 @@ impl TaskForceAI {
                     if let Some(data) = line.strip_prefix("data:") {
                         let data = data.trim();
                         match serde_json::from_str::<TaskStatus>(data) {
-                            Ok(status) => return std::task::Poll::Ready(Some(Ok(status))),
+                            Ok(status) => {
+                                usage.record(&status);
+                                return std::task::Poll::Ready(Some(Ok(status)));
+                            }
                             Err(e) => {
+                                metrics.record_error("Serialization");
                                 return std::task::Poll::Ready(Some(Err(
                                     TaskForceAIError::Serialization(e),
-                                )))
+                                )));
                             }
                         }
                     }
@@ -67,7 +75,8 @@ impl TaskForceAI {
                         continue;
                     }
                     std::task::Poll::Ready(Some(Err(e))) => {
-                        return std::task::Poll::Ready(Some(Err(TaskForceAIError::Network(e))))
+                        metrics.record_error("Network");
+                        return std::task::Poll::Ready(Some(Err(TaskForceAIError::Network(e))));
                     }
                     std::task::Poll::Ready(None) => {
                         if buffer.is_empty() {
@@ -79,11 +88,15 @@ impl TaskForceAI {
                             if let Some(data) = line.strip_prefix("data:") {
                                 let data = data.trim();
                                 match serde_json::from_str::<TaskStatus>(data) {
-                                    Ok(status) => return std::task::Poll::Ready(Some(Ok(status))),
+                                    Ok(status) => {
+                                        usage.record(&status);
+                                        return std::task::Poll::Ready(Some(Ok(status)));
+                                    }
                                     Err(e) => {
+                                        metrics.record_error("Serialization");
                                         return std::task::Poll::Ready(Some(Err(
                                             TaskForceAIError::Serialization(e),
-                                        )))
+                                        )));
                                     }
                                 }
                             }
@@ -98,12 +111,49 @@ impl TaskForceAI {
         Ok(Box::pin(s))
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.run_task_stream",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn run_task_stream(
         &self,
         prompt: &str,
         options: Option<TaskSubmissionOptions>,
     ) -> Result<TaskStatusStream, TaskForceAIError> {
         let task_id = self.submit_task(prompt, options).await?;
+        telemetry::record("task_id", &task_id);
         self.stream_task_status(&task_id).await
     }
+
+    /// Runs a prompt in a thread and streams status updates as the assistant's reply is produced.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.run_in_thread_stream",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn run_in_thread_stream(
+        &self,
+        thread_id: ThreadId,
+        options: ThreadRunOptions,
+    ) -> Result<TaskStatusStream, TaskForceAIError> {
+        let run = self.run_in_thread(thread_id, options).await?;
+        telemetry::record("task_id", &run.task_id);
+        self.stream_task_status(&run.task_id).await
+    }
 }
@@ -0,0 +1,318 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::files::{File, FileUploadOptions, ListFilesQuery};
+use crate::ids::FileId;
+use futures_util::{future, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+/// Name of the manifest written to the synced directory by default.
+pub const DEFAULT_MANIFEST_NAME: &str = ".taskforceai-sync.json";
+
+/// Options for [`TaskForceAI::sync_directory`].
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Delete orphaned remote files: files the manifest recorded for this directory that no
+    /// local file maps to any more
+    pub delete_orphans: bool,
+    /// Also treat remote files with the sync purpose that the manifest never recorded as
+    /// orphans. These include files uploaded by other directories or by
+    /// `upload_attachments`, so combined with `delete_orphans` this prunes the whole account.
+    pub prune_unmanaged: bool,
+    /// Report what would change without uploading, deleting or writing the manifest
+    pub dry_run: bool,
+    /// Where to keep the manifest (default: `.taskforceai-sync.json` in the directory)
+    pub manifest_path: Option<PathBuf>,
+}
+
+/// A local file's last synced state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file_id: FileId,
+    /// Hex-encoded SHA-256 of the content
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// Maps paths relative to the synced directory to the files they were uploaded as.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncManifest {
+    pub files: BTreeMap<String, ManifestEntry>,
+    /// Orphaned remote files not deleted yet, kept so a later sync can still delete them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orphans: Vec<FileId>,
+}
+
+/// A local file that was (or in a dry run, would be) uploaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncedFile {
+    /// Path relative to the synced directory, with `/` separators
+    pub path: String,
+    /// ID of the uploaded file; `None` in a dry run
+    pub file_id: Option<FileId>,
+    pub bytes: u64,
+}
+
+/// A file that could not be synced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// The outcome of a directory sync.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub dry_run: bool,
+    /// Local files that had not been uploaded before
+    pub uploaded: Vec<SyncedFile>,
+    /// Local files whose content changed and were uploaded again
+    pub updated: Vec<SyncedFile>,
+    /// Local files already in sync
+    pub unchanged: Vec<String>,
+    /// Orphaned remote files that were deleted
+    pub deleted: Vec<File>,
+    /// Orphaned remote files, kept because `delete_orphans` is off
+    pub orphans: Vec<File>,
+    pub failed: Vec<SyncFailure>,
+}
+
+impl TaskForceAI {
+    /// Mirrors the files in `local_dir` (recursively) to uploaded files with `purpose`.
+    ///
+    /// Content hashes are kept in a manifest so unchanged files are not uploaded again.
+    /// New and changed files are uploaded. Files the previous manifest recorded that no local
+    /// file maps to any more, including superseded versions of changed files, are orphans.
+    /// Failures on individual files are collected in the report, and their previous
+    /// manifest entries are kept. Orphans that are not deleted stay in the manifest until
+    /// a later sync deletes them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.sync_directory",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn sync_directory(
+        &self,
+        local_dir: impl AsRef<Path>,
+        purpose: &str,
+        options: Option<SyncOptions>,
+    ) -> Result<SyncReport, TaskForceAIError> {
+        let local_dir = local_dir.as_ref();
+        let options = options.unwrap_or_default();
+        let manifest_path = options
+            .manifest_path
+            .clone()
+            .unwrap_or_else(|| local_dir.join(DEFAULT_MANIFEST_NAME));
+
+        let previous = match tokio::fs::read(&manifest_path).await {
+            Ok(data) => serde_json::from_slice::<SyncManifest>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SyncManifest::default(),
+            Err(e) => return Err(self.error(e.into())),
+        };
+
+        let remote: HashMap<FileId, File> = self
+            .files_matching(ListFilesQuery::new().purpose(purpose))
+            .try_filter(|file| future::ready(file.purpose == purpose))
+            .map_ok(|file| (file.id.clone(), file))
+            .try_collect()
+            .await?;
+
+        let local_files = walk(local_dir, &manifest_path)
+            .await
+            .map_err(|e| self.error(e.into()))?;
+
+        let mut report = SyncReport {
+            dry_run: options.dry_run,
+            ..Default::default()
+        };
+        let mut manifest = SyncManifest::default();
+
+        for (relative, path) in local_files {
+            let (sha256, bytes) = match hash_file(&path).await {
+                Ok(hashed) => hashed,
+                Err(e) => {
+                    if let Some(entry) = previous.files.get(&relative) {
+                        manifest.files.insert(relative.clone(), entry.clone());
+                    }
+                    report.failed.push(SyncFailure {
+                        path: relative,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let previous_entry = previous.files.get(&relative);
+            if let Some(entry) = previous_entry {
+                if entry.sha256 == sha256 && remote.contains_key(&entry.file_id) {
+                    manifest.files.insert(relative.clone(), entry.clone());
+                    report.unchanged.push(relative);
+                    continue;
+                }
+            }
+
+            let file_id = if options.dry_run {
+                None
+            } else {
+                match self
+                    .upload_synced_file(&relative, &path, bytes, purpose)
+                    .await
+                {
+                    Ok(file) => Some(file.id),
+                    Err(e) => {
+                        if let Some(entry) = previous_entry {
+                            manifest.files.insert(relative.clone(), entry.clone());
+                        }
+                        report.failed.push(SyncFailure {
+                            path: relative,
+                            error: e.to_string(),
+                        });
+                        continue;
+                    }
+                }
+            };
+
+            if let Some(file_id) = &file_id {
+                manifest.files.insert(
+                    relative.clone(),
+                    ManifestEntry {
+                        file_id: file_id.clone(),
+                        sha256,
+                        bytes,
+                    },
+                );
+            }
+            let synced = SyncedFile {
+                path: relative,
+                file_id,
+                bytes,
+            };
+            match previous_entry {
+                Some(_) => report.updated.push(synced),
+                None => report.uploaded.push(synced),
+            }
+        }
+
+        let kept: HashSet<&str> = manifest
+            .files
+            .values()
+            .map(|entry| entry.file_id.as_str())
+            .collect();
+        let managed: HashSet<&str> = previous
+            .files
+            .values()
+            .map(|entry| &entry.file_id)
+            .chain(&previous.orphans)
+            .map(FileId::as_str)
+            .collect();
+        let mut orphans: Vec<File> = remote
+            .into_values()
+            .filter(|file| !kept.contains(file.id.as_str()))
+            .filter(|file| options.prune_unmanaged || managed.contains(file.id.as_str()))
+            .collect();
+        orphans.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+        for file in orphans {
+            let surviving = if !options.delete_orphans {
+                report.orphans.push(file.clone());
+                Some(file.id)
+            } else if options.dry_run {
+                report.deleted.push(file);
+                None
+            } else {
+                match self.delete_file(&file.id).await {
+                    Ok(()) => {
+                        report.deleted.push(file);
+                        None
+                    }
+                    Err(e) => {
+                        report.failed.push(SyncFailure {
+                            path: file.filename,
+                            error: e.to_string(),
+                        });
+                        Some(file.id)
+                    }
+                }
+            };
+            if let Some(file_id) = surviving.filter(|id| managed.contains(id.as_str())) {
+                manifest.orphans.push(file_id);
+            }
+        }
+
+        if !options.dry_run {
+            tokio::fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?)
+                .await
+                .map_err(|e| self.error(e.into()))?;
+        }
+
+        Ok(report)
+    }
+
+    async fn upload_synced_file(
+        &self,
+        relative: &str,
+        path: &Path,
+        bytes: u64,
+        purpose: &str,
+    ) -> Result<File, TaskForceAIError> {
+        let options = FileUploadOptions {
+            purpose: Some(purpose.to_string()),
+            ..Default::default()
+        };
+        let file = tokio::fs::File::open(path).await?;
+        self.upload_file_from_reader(relative, file, Some(bytes), Some(options))
+            .await
+    }
+}
+
+/// Lists regular files under `root` as (relative path, full path), sorted by relative path.
+async fn walk(root: &Path, skip: &Path) -> std::io::Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() && path != skip {
+                let relative = path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((relative, path));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+async fn hash_file(path: &Path) -> std::io::Result<(String, u64)> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut bytes = 0;
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        bytes += n as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), bytes))
+}
@@ -0,0 +1,45 @@
+//! Helpers for the optional `tracing` feature. Without the feature they compile to no-ops.
+
+/// Records a field on the current span, if the span declared it.
+#[cfg(feature = "tracing")]
+pub(crate) fn record(field: &'static str, value: impl std::fmt::Display) {
+    tracing::Span::current().record(field, tracing::field::display(value));
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record(_field: &'static str, _value: impl std::fmt::Display) {}
+
+/// Adds W3C `traceparent` and `tracestate` headers for the current span's OpenTelemetry context.
+#[cfg(feature = "tracing")]
+pub(crate) fn inject_trace_context(request: &mut reqwest::Request) {
+    use opentelemetry::trace::TraceContextExt;
+    use reqwest::header::HeaderValue;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return;
+    }
+
+    let traceparent = format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    );
+    if let Ok(value) = HeaderValue::from_str(&traceparent) {
+        request.headers_mut().insert("traceparent", value);
+    }
+
+    let tracestate = span_context.trace_state().header();
+    if !tracestate.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&tracestate) {
+            request.headers_mut().insert("tracestate", value);
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn inject_trace_context(_request: &mut reqwest::Request) {}
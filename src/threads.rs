@@ -1,29 +1,39 @@
 use crate::client::TaskForceAI;
 use crate::error::TaskForceAIError;
+use crate::ids::{FileId, MessageId, TaskId, ThreadId};
+use crate::messages::{MessageContent, Role};
+use crate::pagination::{PageRequest, DEFAULT_PAGE_SIZE};
+use crate::telemetry;
+use crate::types::TaskStatus;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 /// Represents a conversation thread.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Thread {
-    pub id: i64,
+    pub id: ThreadId,
     pub title: String,
-    #[serde(with = "chrono::serde::ts_seconds")]
+    #[serde(with = "crate::timestamps::lenient")]
     pub created_at: DateTime<Utc>,
-    #[serde(with = "chrono::serde::ts_seconds")]
+    #[serde(with = "crate::timestamps::lenient")]
     pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
 /// Represents a message within a thread.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ThreadMessage {
-    pub id: i64,
-    pub thread_id: i64,
-    pub role: String, // "user" or "assistant"
-    pub content: String,
-    #[serde(with = "chrono::serde::ts_seconds")]
+    pub id: MessageId,
+    pub thread_id: ThreadId,
+    pub role: Role,
+    pub content: MessageContent,
+    #[serde(with = "crate::timestamps::lenient")]
     pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
 /// Options for creating a thread.
@@ -37,11 +47,23 @@ pub struct CreateThreadOptions {
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Changes to apply to a thread. Fields left as `None` are not modified.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateThreadOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Replaces the thread's metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
 /// Response containing a list of threads.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThreadListResponse {
     pub threads: Vec<Thread>,
     pub total: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Response containing messages from a thread.
@@ -49,6 +71,8 @@ pub struct ThreadListResponse {
 pub struct ThreadMessagesResponse {
     pub messages: Vec<ThreadMessage>,
     pub total: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Options for running a prompt in a thread.
@@ -57,6 +81,10 @@ pub struct ThreadRunOptions {
     pub prompt: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model_id: Option<String>,
+    /// IDs of uploaded files to attach; their purpose must be one of
+    /// [`ATTACHMENT_PURPOSES`](crate::attachments::ATTACHMENT_PURPOSES)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_ids: Option<Vec<FileId>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<HashMap<String, serde_json::Value>>,
 }
@@ -64,13 +92,39 @@ pub struct ThreadRunOptions {
 /// Response from running in a thread.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThreadRunResponse {
-    pub task_id: String,
-    pub thread_id: i64,
-    pub message_id: i64,
+    pub task_id: TaskId,
+    pub thread_id: ThreadId,
+    pub message_id: MessageId,
+}
+
+/// A completed thread run and the assistant reply it added to the thread.
+#[derive(Debug, Clone)]
+pub struct ThreadRunResult {
+    pub status: TaskStatus,
+    /// ID of the user message the prompt was stored as
+    pub prompt_message_id: MessageId,
+    pub message: ThreadMessage,
 }
 
 impl TaskForceAI {
+    /// Validates a thread ID for use in a URL path.
+    pub(crate) fn thread_segment(&self, thread_id: ThreadId) -> Result<String, TaskForceAIError> {
+        thread_id.path_segment().map_err(|e| self.error(e))
+    }
+
     /// Creates a new conversation thread.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.create_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn create_thread(
         &self,
         options: Option<CreateThreadOptions>,
@@ -80,60 +134,387 @@ impl TaskForceAI {
             .transpose()?
             .unwrap_or_else(|| serde_json::json!({}));
 
-        self.request(reqwest::Method::POST, "/threads", Some(body))
-            .await
+        let thread: Thread = self
+            .request(reqwest::Method::POST, "/threads", Some(body))
+            .await?;
+        telemetry::record("thread_id", thread.id);
+        Ok(thread)
     }
 
     /// Retrieves a list of threads.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.list_threads",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn list_threads(
         &self,
         limit: i32,
         offset: i32,
     ) -> Result<ThreadListResponse, TaskForceAIError> {
-        let path = format!("/threads?limit={}&offset={}", limit, offset);
+        let page = PageRequest {
+            limit,
+            offset,
+            cursor: None,
+        };
+        let path = format!("/threads?{}", page.query());
         self.request(reqwest::Method::GET, &path, None).await
     }
 
     /// Retrieves a specific thread by ID.
-    pub async fn get_thread(&self, thread_id: i64) -> Result<Thread, TaskForceAIError> {
-        let path = format!("/threads/{}", thread_id);
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.get_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn get_thread(&self, thread_id: ThreadId) -> Result<Thread, TaskForceAIError> {
+        let path = format!("/threads/{}", self.thread_segment(thread_id)?);
         self.request(reqwest::Method::GET, &path, None).await
     }
 
     /// Deletes a thread by ID.
-    pub async fn delete_thread(&self, thread_id: i64) -> Result<(), TaskForceAIError> {
-        let path = format!("/threads/{}", thread_id);
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.delete_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn delete_thread(&self, thread_id: ThreadId) -> Result<(), TaskForceAIError> {
+        self.invalidate_cached_thread(thread_id).await;
+        let path = format!("/threads/{}", self.thread_segment(thread_id)?);
         let _: serde_json::Value = self.request(reqwest::Method::DELETE, &path, None).await?;
         Ok(())
     }
 
+    /// Renames a thread or replaces its metadata.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.update_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn update_thread(
+        &self,
+        thread_id: ThreadId,
+        options: UpdateThreadOptions,
+    ) -> Result<Thread, TaskForceAIError> {
+        self.invalidate_cached_thread(thread_id).await;
+        let path = format!("/threads/{}", self.thread_segment(thread_id)?);
+        let body = serde_json::to_value(options)?;
+        self.request(reqwest::Method::PATCH, &path, Some(body))
+            .await
+    }
+
     /// Retrieves messages from a thread.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.get_thread_messages",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn get_thread_messages(
         &self,
-        thread_id: i64,
+        thread_id: ThreadId,
         limit: i32,
         offset: i32,
+    ) -> Result<ThreadMessagesResponse, TaskForceAIError> {
+        let page = PageRequest {
+            limit,
+            offset,
+            cursor: None,
+        };
+        if let Some(cached) = self.cached_thread_messages(thread_id, &page).await {
+            return Ok(cached);
+        }
+        self.fetch_thread_messages(thread_id, &page).await
+    }
+
+    /// Fetches a page of a thread's messages from the server, bypassing the local store.
+    async fn fetch_thread_messages(
+        &self,
+        thread_id: ThreadId,
+        page: &PageRequest,
     ) -> Result<ThreadMessagesResponse, TaskForceAIError> {
         let path = format!(
-            "/threads/{}/messages?limit={}&offset={}",
-            thread_id, limit, offset
+            "/threads/{}/messages?{}",
+            self.thread_segment(thread_id)?,
+            page.query()
         );
         self.request(reqwest::Method::GET, &path, None).await
     }
 
+    /// Replaces the content of a message in a thread.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.edit_thread_message",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                message_id = %message_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn edit_thread_message(
+        &self,
+        thread_id: ThreadId,
+        message_id: MessageId,
+        content: impl Into<MessageContent>,
+    ) -> Result<ThreadMessage, TaskForceAIError> {
+        self.invalidate_cached_thread(thread_id).await;
+        let path = format!(
+            "/threads/{}/messages/{}",
+            self.thread_segment(thread_id)?,
+            message_id.path_segment().map_err(|e| self.error(e))?
+        );
+        let body = serde_json::json!({ "content": content.into() });
+        self.request(reqwest::Method::PATCH, &path, Some(body))
+            .await
+    }
+
+    /// Deletes a single message from a thread.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.delete_thread_message",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                message_id = %message_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn delete_thread_message(
+        &self,
+        thread_id: ThreadId,
+        message_id: MessageId,
+    ) -> Result<(), TaskForceAIError> {
+        self.invalidate_cached_thread(thread_id).await;
+        let path = format!(
+            "/threads/{}/messages/{}",
+            self.thread_segment(thread_id)?,
+            message_id.path_segment().map_err(|e| self.error(e))?
+        );
+        let _: serde_json::Value = self.request(reqwest::Method::DELETE, &path, None).await?;
+        Ok(())
+    }
+
     /// Submits a prompt within a thread context.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.run_in_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn run_in_thread(
         &self,
-        thread_id: i64,
+        thread_id: ThreadId,
+        options: ThreadRunOptions,
+    ) -> Result<ThreadRunResponse, TaskForceAIError> {
+        let response = self.start_thread_run(thread_id, options).await?;
+        // The stored copy lacks the reply until the next sync, so drop it
+        self.invalidate_cached_thread(thread_id).await;
+        Ok(response)
+    }
+
+    /// Runs a prompt in a thread, waits for the task to complete and returns the assistant's reply.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.run_in_thread_and_wait",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                task_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn run_in_thread_and_wait(
+        &self,
+        thread_id: ThreadId,
+        options: ThreadRunOptions,
+        poll_interval: Option<Duration>,
+        max_attempts: Option<u32>,
+    ) -> Result<ThreadRunResult, TaskForceAIError> {
+        let started_at = Instant::now();
+        let prompt = MessageContent::Text(options.prompt.clone());
+        let run = self.start_thread_run(thread_id, options).await?;
+        let result = self
+            .finish_thread_run(thread_id, &run, poll_interval, max_attempts)
+            .await;
+        let (status, message) = match result {
+            Ok(finished) => finished,
+            Err(e) => {
+                self.invalidate_cached_thread(thread_id).await;
+                return Err(e);
+            }
+        };
+        self.metrics.record_task_duration(started_at.elapsed());
+
+        // Keep the offline copy browsable; the next sync replaces it with the server's version
+        let prompt = ThreadMessage {
+            id: run.message_id,
+            thread_id,
+            role: Role::User,
+            content: prompt,
+            created_at: Utc::now(),
+            metadata: None,
+        };
+        self.append_cached_messages(thread_id, vec![prompt, message.clone()])
+            .await;
+        Ok(ThreadRunResult {
+            status,
+            prompt_message_id: run.message_id,
+            message,
+        })
+    }
+
+    /// Validates and submits a thread run without touching the local store.
+    async fn start_thread_run(
+        &self,
+        thread_id: ThreadId,
         options: ThreadRunOptions,
     ) -> Result<ThreadRunResponse, TaskForceAIError> {
+        self.check_thread_run(&options)?;
+        if let Some(file_ids) = &options.file_ids {
+            self.validate_file_attachments(file_ids).await?;
+        }
+        self.send_thread_run(thread_id, options).await
+    }
+
+    /// Checks a thread run locally, without any request: prompt and budget.
+    pub(crate) fn check_thread_run(
+        &self,
+        options: &ThreadRunOptions,
+    ) -> Result<(), TaskForceAIError> {
         if options.prompt.trim().is_empty() {
-            return Err(TaskForceAIError::EmptyPrompt);
+            return Err(self.error(TaskForceAIError::EmptyPrompt));
         }
+        self.usage.check_budget().map_err(|e| self.error(e))
+    }
 
-        let path = format!("/threads/{}/runs", thread_id);
+    /// Submits a thread run that has already been checked.
+    pub(crate) async fn send_thread_run(
+        &self,
+        thread_id: ThreadId,
+        options: ThreadRunOptions,
+    ) -> Result<ThreadRunResponse, TaskForceAIError> {
+        let path = format!("/threads/{}/runs", self.thread_segment(thread_id)?);
         let body = serde_json::to_value(options)?;
+        let response: ThreadRunResponse = self
+            .request(reqwest::Method::POST, &path, Some(body))
+            .await?;
+        telemetry::record("task_id", &response.task_id);
+        Ok(response)
+    }
+
+    /// Waits for a thread run's task and looks up the assistant's reply.
+    async fn finish_thread_run(
+        &self,
+        thread_id: ThreadId,
+        run: &ThreadRunResponse,
+        poll_interval: Option<Duration>,
+        max_attempts: Option<u32>,
+    ) -> Result<(TaskStatus, ThreadMessage), TaskForceAIError> {
+        let status = self
+            .wait_for_completion(&run.task_id, poll_interval, max_attempts)
+            .await?;
+        let message = self
+            .reply_after(thread_id, run.message_id)
+            .await?
+            .ok_or_else(|| {
+                self.error(TaskForceAIError::Other(format!(
+                    "No assistant reply found in thread {} for task {}",
+                    thread_id, run.task_id
+                )))
+            })?;
+        Ok((status, message))
+    }
 
-        self.request(reqwest::Method::POST, &path, Some(body)).await
+    /// Finds the last assistant message after `message_id`, reading the thread from the end
+    /// so the cost does not grow with the thread's length.
+    async fn reply_after(
+        &self,
+        thread_id: ThreadId,
+        message_id: MessageId,
+    ) -> Result<Option<ThreadMessage>, TaskForceAIError> {
+        let page_at = |offset: i64| PageRequest {
+            limit: DEFAULT_PAGE_SIZE,
+            offset: offset as i32,
+            cursor: None,
+        };
+        // The local store may not have the reply yet, so always ask the server
+        let first = self.fetch_thread_messages(thread_id, &page_at(0)).await?;
+        let mut offset = (first.total - i64::from(DEFAULT_PAGE_SIZE)).max(0);
+        let mut page = match offset {
+            0 => first,
+            _ => {
+                self.fetch_thread_messages(thread_id, &page_at(offset))
+                    .await?
+            }
+        };
+        loop {
+            let reply = page
+                .messages
+                .iter()
+                .rev()
+                .find(|m| m.role == Role::Assistant && m.id > message_id);
+            if let Some(reply) = reply {
+                return Ok(Some(reply.clone()));
+            }
+            // Earlier pages can only hold the reply if this one starts after the prompt
+            let passed_prompt = page.messages.first().is_none_or(|m| m.id <= message_id);
+            if offset == 0 || passed_prompt {
+                return Ok(None);
+            }
+            offset = (offset - i64::from(DEFAULT_PAGE_SIZE)).max(0);
+            page = self
+                .fetch_thread_messages(thread_id, &page_at(offset))
+                .await?;
+        }
     }
 }
@@ -0,0 +1,168 @@
+//! Timestamp parsing shared by the API models.
+//!
+//! The server has sent timestamps as Unix seconds, Unix milliseconds and RFC 3339 strings.
+//! [`lenient`] accepts all three. Whole-second timestamps are serialized as Unix seconds;
+//! anything finer is written as RFC 3339 so it survives a round trip.
+//! With the optional `time` feature, [`time_lenient`] does the same for
+//! `time::OffsetDateTime`, and [`to_time`]/[`from_time`] convert between the two crates.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::{self, Visitor};
+use serde::Deserializer;
+use std::fmt;
+
+/// Unix timestamps at or above this magnitude are taken to be milliseconds. In seconds it
+/// is the year 5138; in milliseconds, March 1973.
+const MILLIS_THRESHOLD: i64 = 100_000_000_000;
+
+/// Serde helpers for a `DateTime<Utc>` sent as seconds, milliseconds or RFC 3339.
+pub mod lenient {
+    use super::*;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if time.timestamp_subsec_nanos() == 0 {
+            serializer.serialize_i64(time.timestamp())
+        } else {
+            serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+struct TimestampVisitor;
+
+impl TimestampVisitor {
+    fn from_number<E: de::Error>(value: i64) -> Result<DateTime<Utc>, E> {
+        let time = if value.abs() >= MILLIS_THRESHOLD {
+            DateTime::from_timestamp_millis(value)
+        } else {
+            DateTime::from_timestamp(value, 0)
+        };
+        time.ok_or_else(|| E::custom(format!("timestamp {} is out of range", value)))
+    }
+}
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = DateTime<Utc>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Unix seconds, Unix milliseconds or an RFC 3339 string")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Self::from_number(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        let value = i64::try_from(value)
+            .map_err(|_| E::custom(format!("timestamp {} is out of range", value)))?;
+        Self::from_number(value)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        if value.abs() >= MILLIS_THRESHOLD as f64 {
+            return Self::from_number(value.round() as i64);
+        }
+        let nanos = (value.fract() * 1e9).round() as u32;
+        DateTime::from_timestamp(value.trunc() as i64, nanos)
+            .ok_or_else(|| E::custom(format!("timestamp {} is out of range", value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        let value = value.trim();
+        if let Ok(number) = value.parse::<i64>() {
+            return Self::from_number(number);
+        }
+        DateTime::parse_from_rfc3339(value)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|e| E::custom(format!("invalid timestamp {:?}: {}", value, e)))
+    }
+}
+
+/// Converts a chrono timestamp to a `time::OffsetDateTime` in UTC.
+#[cfg(feature = "time")]
+pub fn to_time(time: DateTime<Utc>) -> time::OffsetDateTime {
+    let nanos = time
+        .timestamp_nanos_opt()
+        .map(i128::from)
+        .unwrap_or_else(|| i128::from(time.timestamp()) * 1_000_000_000);
+    time::OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .unwrap_or(time::OffsetDateTime::UNIX_EPOCH)
+}
+
+/// Converts a `time::OffsetDateTime` to a chrono timestamp in UTC.
+#[cfg(feature = "time")]
+pub fn from_time(time: time::OffsetDateTime) -> DateTime<Utc> {
+    DateTime::from_timestamp(time.unix_timestamp(), time.nanosecond()).unwrap_or_default()
+}
+
+/// Serde helpers for a `time::OffsetDateTime` sent as seconds, milliseconds or RFC 3339.
+#[cfg(feature = "time")]
+pub mod time_lenient {
+    use super::*;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(
+        time: &time::OffsetDateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        lenient::serialize(&from_time(*time), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<time::OffsetDateTime, D::Error> {
+        deserializer.deserialize_any(TimestampVisitor).map(to_time)
+    }
+}
+
+#[cfg(feature = "time")]
+impl crate::files::File {
+    /// [`created_at`](Self::created_at) as a `time::OffsetDateTime`.
+    pub fn created_at_time(&self) -> time::OffsetDateTime {
+        to_time(self.created_at)
+    }
+}
+
+#[cfg(feature = "time")]
+impl crate::files::ListFilesQuery {
+    /// Like [`created_after`](Self::created_after), taking a `time::OffsetDateTime`.
+    pub fn created_after_time(self, created_after: time::OffsetDateTime) -> Self {
+        self.created_after(from_time(created_after))
+    }
+
+    /// Like [`created_before`](Self::created_before), taking a `time::OffsetDateTime`.
+    pub fn created_before_time(self, created_before: time::OffsetDateTime) -> Self {
+        self.created_before(from_time(created_before))
+    }
+}
+
+#[cfg(feature = "time")]
+impl crate::threads::Thread {
+    /// [`created_at`](Self::created_at) as a `time::OffsetDateTime`.
+    pub fn created_at_time(&self) -> time::OffsetDateTime {
+        to_time(self.created_at)
+    }
+
+    /// [`updated_at`](Self::updated_at) as a `time::OffsetDateTime`.
+    pub fn updated_at_time(&self) -> time::OffsetDateTime {
+        to_time(self.updated_at)
+    }
+}
+
+#[cfg(feature = "time")]
+impl crate::threads::ThreadMessage {
+    /// [`created_at`](Self::created_at) as a `time::OffsetDateTime`.
+    pub fn created_at_time(&self) -> time::OffsetDateTime {
+        to_time(self.created_at)
+    }
+}
@@ -0,0 +1,290 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::ids::{MessageId, ThreadId};
+use crate::messages::{ContentPart, MessageContent, Role};
+use crate::threads::{CreateThreadOptions, Thread, ThreadMessage};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File formats a thread transcript can be exported to and imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A single JSON document holding the thread and its messages
+    Json,
+    /// The thread on the first line, then one message per line
+    Jsonl,
+    /// A human-readable transcript. Only the text of messages survives an import;
+    /// other content parts are rendered as placeholders.
+    Markdown,
+}
+
+/// A thread together with all of its messages.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadTranscript {
+    pub thread: Thread,
+    pub messages: Vec<ThreadMessage>,
+}
+
+impl ThreadTranscript {
+    /// Serializes the transcript in the given format.
+    pub fn render(&self, format: ExportFormat) -> Result<String, TaskForceAIError> {
+        match format {
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            ExportFormat::Jsonl => {
+                let mut out = serde_json::to_string(&self.thread)?;
+                out.push('\n');
+                for message in &self.messages {
+                    out.push_str(&serde_json::to_string(message)?);
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+            ExportFormat::Markdown => Ok(self.render_markdown()),
+        }
+    }
+
+    /// Parses a transcript previously produced by [`render`](Self::render).
+    pub fn parse(data: &str, format: ExportFormat) -> Result<Self, TaskForceAIError> {
+        match format {
+            ExportFormat::Json => Ok(serde_json::from_str(data)?),
+            ExportFormat::Jsonl => {
+                let mut lines = data.lines().filter(|line| !line.trim().is_empty());
+                let thread = lines.next().ok_or_else(|| {
+                    TaskForceAIError::Other("JSONL transcript is empty".to_string())
+                })?;
+                Ok(Self {
+                    thread: serde_json::from_str(thread)?,
+                    messages: lines.map(serde_json::from_str).collect::<Result<_, _>>()?,
+                })
+            }
+            ExportFormat::Markdown => parse_markdown(data),
+        }
+    }
+
+    fn render_markdown(&self) -> String {
+        let thread = &self.thread;
+        // A line break in the title would end the heading early
+        let title = thread.title.lines().collect::<Vec<_>>().join(" ");
+        let mut out = format!(
+            "# {}\n\n- Thread ID: {}\n- Created: {}\n- Updated: {}\n",
+            title,
+            thread.id,
+            timestamp(&thread.created_at),
+            timestamp(&thread.updated_at)
+        );
+        if let Some(metadata) = &thread.metadata {
+            let metadata = serde_json::to_string(metadata).unwrap_or_default();
+            out.push_str(&format!("- Metadata: `{}`\n", metadata));
+        }
+        for message in &self.messages {
+            out.push_str(&format!(
+                "\n### {} · {}\n\n",
+                message.role,
+                timestamp(&message.created_at)
+            ));
+            for part in message.content.parts() {
+                out.push_str(&markdown_part(&part));
+                out.push_str("\n\n");
+            }
+        }
+        out
+    }
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Escapes a message body line that would otherwise read as a message heading. Lines
+/// already starting with a backslash are escaped too, so unescaping is unambiguous.
+fn escape_line(line: &str) -> String {
+    if line.starts_with("### ") || line.starts_with('\\') {
+        format!("\\{}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+fn unescape_line(line: &str) -> &str {
+    line.strip_prefix('\\').unwrap_or(line)
+}
+
+fn markdown_part(part: &ContentPart) -> String {
+    match part {
+        ContentPart::Text { text } => text
+            .trim_end()
+            .lines()
+            .map(escape_line)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ContentPart::Image(image) => format!(
+            "[image: {} ({})]",
+            image.name.as_deref().unwrap_or("unnamed"),
+            image.mime_type
+        ),
+        ContentPart::File { file_id, filename } => match filename {
+            Some(filename) => format!("[file: {} ({})]", filename, file_id),
+            None => format!("[file: {}]", file_id),
+        },
+        ContentPart::ToolCall(call) => format!("[tool call: {}({})]", call.name, call.arguments),
+        ContentPart::ToolResult(result) => {
+            format!(
+                "[tool result for {}: {}]",
+                result.tool_call_id, result.content
+            )
+        }
+        ContentPart::Other(value) => format!("[{}]", value),
+    }
+}
+
+fn parse_markdown(data: &str) -> Result<ThreadTranscript, TaskForceAIError> {
+    let invalid =
+        |reason: &str| TaskForceAIError::Other(format!("Invalid Markdown transcript: {}", reason));
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value.trim())
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|_| invalid("bad timestamp"))
+    };
+
+    let mut lines = data.lines().peekable();
+    let title = lines
+        .next()
+        .and_then(|line| line.strip_prefix("# "))
+        .ok_or_else(|| invalid("missing title"))?;
+    let mut thread = Thread {
+        id: ThreadId::default(),
+        title: title.to_string(),
+        created_at: DateTime::<Utc>::default(),
+        updated_at: DateTime::<Utc>::default(),
+        metadata: None,
+    };
+
+    while let Some(line) = lines.next_if(|line| !line.starts_with("### ")) {
+        if let Some(id) = line.strip_prefix("- Thread ID: ") {
+            thread.id = id.trim().parse().map_err(|_| invalid("bad thread ID"))?;
+        } else if let Some(created) = line.strip_prefix("- Created: ") {
+            thread.created_at = parse_time(created)?;
+        } else if let Some(updated) = line.strip_prefix("- Updated: ") {
+            thread.updated_at = parse_time(updated)?;
+        } else if let Some(metadata) = line.strip_prefix("- Metadata: ") {
+            thread.metadata = Some(serde_json::from_str(metadata.trim().trim_matches('`'))?);
+        }
+    }
+
+    let mut messages = Vec::new();
+    while let Some(heading) = lines.next() {
+        let (role, created_at) = heading
+            .strip_prefix("### ")
+            .and_then(|heading| heading.split_once(" · "))
+            .ok_or_else(|| invalid("bad message heading"))?;
+        let mut body = Vec::new();
+        while let Some(line) = lines.next_if(|line| !line.starts_with("### ")) {
+            body.push(unescape_line(line));
+        }
+        // Drop the blank line after the heading; the separators after the body are
+        // trimmed, as rendering trimmed the body's own trailing whitespace
+        if body.first() == Some(&"") {
+            body.remove(0);
+        }
+        messages.push(ThreadMessage {
+            id: MessageId(messages.len() as i64 + 1),
+            thread_id: thread.id,
+            role: Role::from(role),
+            content: MessageContent::Text(body.join("\n").trim_end().to_string()),
+            created_at: parse_time(created_at)?,
+            metadata: None,
+        });
+    }
+
+    Ok(ThreadTranscript { thread, messages })
+}
+
+impl TaskForceAI {
+    /// Fetches a thread and every one of its messages.
+    pub async fn thread_transcript(
+        &self,
+        thread_id: ThreadId,
+    ) -> Result<ThreadTranscript, TaskForceAIError> {
+        let thread = self.get_thread(thread_id).await?;
+        let messages = self.thread_messages(thread_id).try_collect().await?;
+        Ok(ThreadTranscript { thread, messages })
+    }
+
+    /// Exports a thread's full transcript, including metadata and timestamps.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.export_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = %thread_id,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn export_thread(
+        &self,
+        thread_id: ThreadId,
+        format: ExportFormat,
+    ) -> Result<String, TaskForceAIError> {
+        self.thread_transcript(thread_id)
+            .await?
+            .render(format)
+            .map_err(|e| self.error(e))
+    }
+
+    /// Exports a thread's transcript and writes it to `path`.
+    pub async fn export_thread_to_path(
+        &self,
+        thread_id: ThreadId,
+        format: ExportFormat,
+        path: impl AsRef<Path>,
+    ) -> Result<(), TaskForceAIError> {
+        let transcript = self.export_thread(thread_id, format).await?;
+        tokio::fs::write(path, transcript)
+            .await
+            .map_err(|e| self.error(e.into()))
+    }
+
+    /// Recreates a thread from an exported transcript. The new thread gets its own ID.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.import_thread",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                thread_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn import_thread(
+        &self,
+        data: &str,
+        format: ExportFormat,
+    ) -> Result<Thread, TaskForceAIError> {
+        let transcript = ThreadTranscript::parse(data, format).map_err(|e| self.error(e))?;
+        self.create_thread(Some(CreateThreadOptions {
+            title: Some(transcript.thread.title),
+            messages: Some(transcript.messages),
+            metadata: transcript.thread.metadata,
+        }))
+        .await
+    }
+
+    /// Reads a transcript from `path` and recreates the thread.
+    pub async fn import_thread_from_path(
+        &self,
+        path: impl AsRef<Path>,
+        format: ExportFormat,
+    ) -> Result<Thread, TaskForceAIError> {
+        let data = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| self.error(e.into()))?;
+        self.import_thread(&data, format).await
+    }
+}
@@ -1,3 +1,5 @@
+use crate::error::TaskForceAIError;
+use crate::ids::{FileId, TaskId};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -11,10 +13,13 @@ pub struct TaskForceAIOptions {
     pub timeout: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mock_mode: Option<bool>,
+    /// Files larger than this many bytes are uploaded in parts by `upload_file_from_path`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunked_upload_threshold: Option<u64>,
 }
 
 /// A base64-encoded image attachment to include with a task prompt.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImageAttachment {
     /// Base64-encoded image data
     pub data: String,
@@ -28,11 +33,97 @@ pub struct ImageAttachment {
     pub detail: Option<String>,
 }
 
+/// Highest sampling temperature accepted by [`TaskSubmissionOptions::temperature`].
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// Most agents a single task may run with.
+pub const MAX_AGENTS: u32 = 16;
+
+/// How much reasoning the models spend before answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+/// The shape of the task result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text (the default)
+    Text,
+    /// Any valid JSON object
+    JsonObject,
+    /// JSON matching the given JSON Schema
+    JsonSchema {
+        name: String,
+        schema: serde_json::Value,
+    },
+}
+
+/// Scheduling priority of a task relative to the account's other tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Wire names of the typed [`TaskSubmissionOptions`] fields. `additional_options` may use
+/// them only while the typed field is unset.
+pub(crate) const TYPED_OPTION_KEYS: &[&str] = &[
+    "modelId",
+    "systemPrompt",
+    "temperature",
+    "maxOutputTokens",
+    "agentCount",
+    "agentRoles",
+    "reasoningEffort",
+    "responseFormat",
+    "tags",
+    "metadata",
+    "priority",
+    "silent",
+    "mock",
+    "vercelAiKey",
+    "fileIds",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskSubmissionOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model_id: Option<String>,
+    /// Instructions that take precedence over the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Sampling temperature, from 0 to [`MAX_TEMPERATURE`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Upper bound on tokens in the result; must be positive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    /// Number of agents working on the task, from 1 to [`MAX_AGENTS`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_count: Option<u32>,
+    /// A role for each agent (e.g. "researcher", "critic"); at most `agent_count` entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_roles: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Labels for filtering and reporting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Arbitrary caller data stored with the task and returned in its status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<TaskPriority>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub silent: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -42,10 +133,123 @@ pub struct TaskSubmissionOptions {
     /// Image attachments to include with the prompt
     #[serde(skip)]
     pub images: Option<Vec<ImageAttachment>>,
+    /// IDs of uploaded files to attach; their purpose must be one of
+    /// [`ATTACHMENT_PURPOSES`](crate::attachments::ATTACHMENT_PURPOSES)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_ids: Option<Vec<FileId>>,
+    /// Settings without a typed field, sent as-is
     #[serde(flatten)]
     pub additional_options: HashMap<String, serde_json::Value>,
 }
 
+impl TaskSubmissionOptions {
+    /// Rejects values the API would refuse, before anything is sent.
+    ///
+    /// Typed settings passed through `additional_options` are checked like the typed fields.
+    pub fn validate(&self) -> Result<(), TaskForceAIError> {
+        self.check_values()?;
+
+        let mut passthrough = false;
+        for key in self.additional_options.keys() {
+            if !TYPED_OPTION_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            // A flattened key would be sent twice alongside the typed field
+            if self.typed_field_is_set(key) {
+                return Err(invalid(format!(
+                    "additional_options key \"{}\" conflicts with a typed field",
+                    key
+                )));
+            }
+            passthrough = true;
+        }
+        if passthrough {
+            let merged: Self = serde_json::from_value(serde_json::to_value(self)?)
+                .map_err(|e| invalid(format!("invalid additional_options: {}", e)))?;
+            merged.check_values()?;
+        }
+        Ok(())
+    }
+
+    fn typed_field_is_set(&self, key: &str) -> bool {
+        match key {
+            "modelId" => self.model_id.is_some(),
+            "systemPrompt" => self.system_prompt.is_some(),
+            "temperature" => self.temperature.is_some(),
+            "maxOutputTokens" => self.max_output_tokens.is_some(),
+            "agentCount" => self.agent_count.is_some(),
+            "agentRoles" => self.agent_roles.is_some(),
+            "reasoningEffort" => self.reasoning_effort.is_some(),
+            "responseFormat" => self.response_format.is_some(),
+            "tags" => self.tags.is_some(),
+            "metadata" => self.metadata.is_some(),
+            "priority" => self.priority.is_some(),
+            "silent" => self.silent.is_some(),
+            "mock" => self.mock.is_some(),
+            "vercelAiKey" => self.vercel_ai_key.is_some(),
+            "fileIds" => self.file_ids.is_some(),
+            _ => false,
+        }
+    }
+
+    fn check_values(&self) -> Result<(), TaskForceAIError> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=MAX_TEMPERATURE).contains(&temperature) {
+                return Err(invalid(format!(
+                    "temperature must be between 0 and {}, got {}",
+                    MAX_TEMPERATURE, temperature
+                )));
+            }
+        }
+        if self.max_output_tokens == Some(0) {
+            return Err(invalid("max_output_tokens must be positive".to_string()));
+        }
+        if let Some(agent_count) = self.agent_count {
+            if !(1..=MAX_AGENTS).contains(&agent_count) {
+                return Err(invalid(format!(
+                    "agent_count must be between 1 and {}, got {}",
+                    MAX_AGENTS, agent_count
+                )));
+            }
+        }
+        if let Some(roles) = &self.agent_roles {
+            let max = self.agent_count.unwrap_or(MAX_AGENTS) as usize;
+            if roles.len() > max {
+                return Err(invalid(format!(
+                    "{} agent_roles given for at most {} agents",
+                    roles.len(),
+                    max
+                )));
+            }
+            if roles.iter().any(|role| role.trim().is_empty()) {
+                return Err(invalid("agent_roles must not be blank".to_string()));
+            }
+        }
+        if let Some(ResponseFormat::JsonSchema { name, schema }) = &self.response_format {
+            if name.trim().is_empty() {
+                return Err(invalid(
+                    "response_format schema name must not be blank".to_string(),
+                ));
+            }
+            if !schema.is_object() {
+                return Err(invalid(
+                    "response_format schema must be a JSON object".to_string(),
+                ));
+            }
+        }
+        if let Some(tags) = &self.tags {
+            if tags.iter().any(|tag| tag.trim().is_empty()) {
+                return Err(invalid("tags must not be blank".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> TaskForceAIError {
+    TaskForceAIError::InvalidOptions(message)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum TaskStatusValue {
@@ -57,7 +261,7 @@ pub enum TaskStatusValue {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskStatus {
-    pub task_id: String,
+    pub task_id: TaskId,
     pub status: TaskStatusValue,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<String>,
@@ -72,5 +276,5 @@ pub struct TaskStatus {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubmitTaskResponse {
     #[serde(rename = "taskId")]
-    pub task_id: String,
+    pub task_id: TaskId,
 }
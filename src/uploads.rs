@@ -0,0 +1,382 @@
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::files::{File, FileUploadOptions, TransferProgress};
+use crate::ids::encode_path_segment;
+use crate::{mime, telemetry};
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+pub const DEFAULT_CHUNKED_UPLOAD_THRESHOLD: u64 = 100 * 1024 * 1024;
+pub const DEFAULT_PART_SIZE: u64 = 16 * 1024 * 1024;
+pub const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// Options for a chunked upload.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkedUploadOptions {
+    /// Size of each part in bytes (default: 16 MiB)
+    pub part_size: Option<u64>,
+    /// Number of parts sent in parallel (default: 4)
+    pub concurrency: Option<usize>,
+    /// Where to persist the [`ResumeToken`]. If a matching token already exists there,
+    /// the upload resumes from it; the file is removed once the upload completes.
+    pub resume_token_path: Option<PathBuf>,
+}
+
+/// A part the server has received and verified.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadedPart {
+    pub part_number: u32,
+    /// Hex-encoded SHA-256 of the part
+    pub checksum: String,
+}
+
+/// The state of an in-progress chunked upload, persisted so it can continue after a restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeToken {
+    pub upload_id: String,
+    pub path: PathBuf,
+    pub filename: String,
+    pub bytes: u64,
+    /// Modification time of the file when the upload started. A file edited since then
+    /// is uploaded from scratch rather than resumed.
+    #[serde(default)]
+    pub modified: Option<SystemTime>,
+    pub part_size: u64,
+    pub completed_parts: Vec<UploadedPart>,
+}
+
+impl ResumeToken {
+    /// Loads a token previously persisted with [`ResumeToken::save`].
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, TaskForceAIError> {
+        let data = tokio::fs::read(path).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<(), TaskForceAIError> {
+        tokio::fs::write(path, serde_json::to_vec(self)?).await?;
+        Ok(())
+    }
+
+    pub fn part_count(&self) -> u32 {
+        self.bytes.div_ceil(self.part_size).max(1) as u32
+    }
+
+    /// Rejects a token whose parts do not fit its file, e.g. one edited on disk.
+    fn check(&self) -> Result<(), String> {
+        if self.part_size == 0 {
+            return Err("part_size must be positive".to_string());
+        }
+        if self.bytes.div_ceil(self.part_size) > u64::from(u32::MAX) {
+            return Err(format!(
+                "{} bytes in parts of {} is too many parts",
+                self.bytes, self.part_size
+            ));
+        }
+        let part_count = self.part_count();
+        match self
+            .completed_parts
+            .iter()
+            .find(|part| !(1..=part_count).contains(&part.part_number))
+        {
+            Some(part) => Err(format!(
+                "completed part {} is outside 1..={}",
+                part.part_number, part_count
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn part_range(&self, part_number: u32) -> (u64, u64) {
+        let offset = u64::from(part_number - 1) * self.part_size;
+        (offset, self.part_size.min(self.bytes - offset))
+    }
+
+    /// Whether the file on disk still has the size and modification time recorded here.
+    async fn matches_file(&self) -> bool {
+        match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => {
+                metadata.len() == self.bytes && metadata.modified().ok() == self.modified
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn completed_bytes(&self) -> u64 {
+        self.completed_parts
+            .iter()
+            .map(|part| self.part_range(part.part_number).1)
+            .sum()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadSession {
+    upload_id: String,
+    #[serde(default)]
+    part_size: Option<u64>,
+}
+
+impl TaskForceAI {
+    /// Uploads a file from disk in parts sent in parallel, verifying each part's checksum.
+    ///
+    /// `upload_file_from_path` switches to this automatically above the client's
+    /// chunked upload threshold.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.upload_file_chunked",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                upload_id = tracing::field::Empty,
+                file_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn upload_file_chunked(
+        &self,
+        path: impl AsRef<Path>,
+        options: Option<FileUploadOptions>,
+        chunked: Option<ChunkedUploadOptions>,
+    ) -> Result<File, TaskForceAIError> {
+        let path = path.as_ref();
+        let options = options.unwrap_or_default();
+        let chunked = chunked.unwrap_or_default();
+
+        let metadata = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| self.error(e.into()))?;
+        let bytes = metadata.len();
+        let modified = metadata.modified().ok();
+
+        let existing = match &chunked.resume_token_path {
+            Some(token_path) if token_path.exists() => {
+                ResumeToken::load(token_path).await.ok().filter(|token| {
+                    token.path == path
+                        && token.bytes == bytes
+                        && token.modified == modified
+                        && token.check().is_ok()
+                })
+            }
+            _ => None,
+        };
+
+        let token = match existing {
+            Some(token) => token,
+            None => {
+                let token = self
+                    .initiate_upload(path, bytes, modified, &options, &chunked)
+                    .await?;
+                if let Some(token_path) = &chunked.resume_token_path {
+                    token.save(token_path).await.map_err(|e| self.error(e))?;
+                }
+                token
+            }
+        };
+
+        self.resume_upload(token, Some(options), Some(chunked))
+            .await
+    }
+
+    /// Continues a chunked upload from a [`ResumeToken`], sending only the missing parts.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "taskforceai.resume_upload",
+            skip_all,
+            fields(
+                endpoint = tracing::field::Empty,
+                upload_id = %token.upload_id,
+                file_id = tracing::field::Empty,
+                http.status_code = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn resume_upload(
+        &self,
+        mut token: ResumeToken,
+        options: Option<FileUploadOptions>,
+        chunked: Option<ChunkedUploadOptions>,
+    ) -> Result<File, TaskForceAIError> {
+        let options = options.unwrap_or_default();
+        let chunked = chunked.unwrap_or_default();
+        let concurrency = chunked
+            .concurrency
+            .unwrap_or(DEFAULT_UPLOAD_CONCURRENCY)
+            .max(1);
+        telemetry::record("upload_id", &token.upload_id);
+        token.check().map_err(|e| {
+            self.error(TaskForceAIError::Other(format!(
+                "invalid resume token: {}",
+                e
+            )))
+        })?;
+        if !token.matches_file().await {
+            return Err(self.error(TaskForceAIError::Other(format!(
+                "{} changed since the upload started",
+                token.path.display()
+            ))));
+        }
+
+        let pending: Vec<u32> = (1..=token.part_count())
+            .filter(|n| !token.completed_parts.iter().any(|p| p.part_number == *n))
+            .collect();
+
+        let mut transferred = token.completed_bytes();
+        let shared = token.clone();
+        let mut parts = stream::iter(pending)
+            .map(|part_number| self.upload_part(&shared, part_number))
+            .buffer_unordered(concurrency);
+
+        while let Some(result) = parts.next().await {
+            let part = result?;
+            let size = token.part_range(part.part_number).1;
+            token.completed_parts.push(part);
+            if let Some(token_path) = &chunked.resume_token_path {
+                token.save(token_path).await.map_err(|e| self.error(e))?;
+            }
+
+            transferred += size;
+            self.metrics.record_bytes_uploaded(size);
+            if let Some(progress) = &options.progress {
+                progress.report(TransferProgress {
+                    bytes_transferred: transferred,
+                    total_bytes: Some(token.bytes),
+                });
+            }
+        }
+        drop(parts);
+
+        token.completed_parts.sort_by_key(|p| p.part_number);
+        let url = format!(
+            "{}/uploads/{}/complete",
+            self.base_url,
+            encode_path_segment(&token.upload_id, "Upload ID").map_err(|e| self.error(e))?
+        );
+        let request = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "parts": token.completed_parts }));
+        let response = self.send(request).await?;
+        let file: File = response.json().await.map_err(|e| self.error(e.into()))?;
+
+        if let Some(token_path) = &chunked.resume_token_path {
+            let _ = tokio::fs::remove_file(token_path).await;
+        }
+        telemetry::record("file_id", &file.id);
+        Ok(file)
+    }
+
+    async fn initiate_upload(
+        &self,
+        path: &Path,
+        bytes: u64,
+        modified: Option<SystemTime>,
+        options: &FileUploadOptions,
+        chunked: &ChunkedUploadOptions,
+    ) -> Result<ResumeToken, TaskForceAIError> {
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| {
+                self.error(TaskForceAIError::Other(format!(
+                    "{} is not a file",
+                    path.display()
+                )))
+            })?;
+
+        let mime_type = match &options.mime_type {
+            Some(mime_type) => mime_type.clone(),
+            None => {
+                let mut head = Vec::with_capacity(mime::SNIFF_LEN);
+                tokio::fs::File::open(path)
+                    .await
+                    .map_err(|e| self.error(e.into()))?
+                    .take(mime::SNIFF_LEN as u64)
+                    .read_to_end(&mut head)
+                    .await
+                    .map_err(|e| self.error(e.into()))?;
+                mime::detect(&filename, &head)
+            }
+        };
+
+        let part_size = chunked.part_size.unwrap_or(DEFAULT_PART_SIZE);
+        if part_size == 0 {
+            return Err(self.error(TaskForceAIError::Other(
+                "Part size must be greater than zero".to_string(),
+            )));
+        }
+
+        let url = format!("{}/uploads", self.base_url);
+        let request = self.client.post(&url).json(&serde_json::json!({
+            "filename": filename,
+            "bytes": bytes,
+            "purpose": options.purpose,
+            "mime_type": mime_type,
+            "part_size": part_size,
+        }));
+        let response = self.send(request).await?;
+        let session: UploadSession = response.json().await.map_err(|e| self.error(e.into()))?;
+
+        Ok(ResumeToken {
+            upload_id: session.upload_id,
+            path: path.to_path_buf(),
+            filename,
+            bytes,
+            modified,
+            part_size: session.part_size.filter(|s| *s > 0).unwrap_or(part_size),
+            completed_parts: Vec::new(),
+        })
+    }
+
+    async fn upload_part(
+        &self,
+        token: &ResumeToken,
+        part_number: u32,
+    ) -> Result<UploadedPart, TaskForceAIError> {
+        let (offset, length) = token.part_range(part_number);
+        let mut file = tokio::fs::File::open(&token.path)
+            .await
+            .map_err(|e| self.error(e.into()))?;
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(|e| self.error(e.into()))?;
+        let mut data = vec![0; length as usize];
+        file.read_exact(&mut data)
+            .await
+            .map_err(|e| self.error(e.into()))?;
+
+        let checksum = format!("{:x}", Sha256::digest(&data));
+        let url = format!(
+            "{}/uploads/{}/parts/{}",
+            self.base_url,
+            encode_path_segment(&token.upload_id, "Upload ID").map_err(|e| self.error(e))?,
+            part_number
+        );
+        let request = self
+            .client
+            .put(&url)
+            .header("Content-Type", "application/octet-stream")
+            .header("x-checksum-sha256", &checksum)
+            .body(data);
+        let response = self.send(request).await?;
+        let part: UploadedPart = response.json().await.map_err(|e| self.error(e.into()))?;
+
+        if part.checksum != checksum {
+            return Err(self.error(TaskForceAIError::ChecksumMismatch {
+                expected: checksum,
+                actual: part.checksum,
+            }));
+        }
+        Ok(UploadedPart {
+            part_number,
+            checksum,
+        })
+    }
+}
@@ -0,0 +1,205 @@
+use crate::budget::{Budget, BudgetLedger, BudgetStatus};
+use crate::client::TaskForceAI;
+use crate::error::TaskForceAIError;
+use crate::ids::TaskId;
+use crate::types::{TaskStatus, TaskStatusValue};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+/// Token and cost usage for one agent within a task.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentUsage {
+    #[serde(alias = "name")]
+    pub agent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "model_id")]
+    pub model_id: Option<String>,
+    #[serde(default, alias = "input_tokens", alias = "promptTokens")]
+    pub input_tokens: u64,
+    #[serde(default, alias = "output_tokens", alias = "completionTokens")]
+    pub output_tokens: u64,
+    /// Cost in USD
+    #[serde(default, alias = "costUsd")]
+    pub cost: f64,
+}
+
+/// Token and cost usage reported by the server for a task, or summed across tasks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    #[serde(default, alias = "input_tokens", alias = "promptTokens")]
+    pub input_tokens: u64,
+    #[serde(default, alias = "output_tokens", alias = "completionTokens")]
+    pub output_tokens: u64,
+    /// Cost in USD
+    #[serde(default, alias = "costUsd")]
+    pub cost: f64,
+    /// IDs of the models that contributed to the usage
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Per-agent breakdown, if the server reported one
+    #[serde(default)]
+    pub agents: Vec<AgentUsage>,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// Adds `other` into this usage. Agents with the same name and model are merged.
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost += other.cost;
+
+        for model in &other.models {
+            if !self.models.contains(model) {
+                self.models.push(model.clone());
+            }
+        }
+
+        for agent in &other.agents {
+            match self
+                .agents
+                .iter_mut()
+                .find(|a| a.agent == agent.agent && a.model_id == agent.model_id)
+            {
+                Some(existing) => {
+                    existing.input_tokens += agent.input_tokens;
+                    existing.output_tokens += agent.output_tokens;
+                    existing.cost += agent.cost;
+                }
+                None => self.agents.push(agent.clone()),
+            }
+        }
+    }
+
+    /// Fills totals and model IDs from the agent breakdown when the server omitted them.
+    fn normalize(mut self) -> Self {
+        if self.input_tokens == 0 && self.output_tokens == 0 && self.cost == 0.0 {
+            for agent in &self.agents {
+                self.input_tokens += agent.input_tokens;
+                self.output_tokens += agent.output_tokens;
+                self.cost += agent.cost;
+            }
+        }
+        if self.models.is_empty() {
+            for model in self.agents.iter().filter_map(|a| a.model_id.as_ref()) {
+                if !self.models.contains(model) {
+                    self.models.push(model.clone());
+                }
+            }
+        }
+        self
+    }
+}
+
+impl TaskStatus {
+    /// Parses the `usage` entry of the task metadata, if present and well-formed.
+    pub fn usage(&self) -> Option<Usage> {
+        let value = self.metadata.as_ref()?.get("usage")?;
+        serde_json::from_value::<Usage>(value.clone())
+            .ok()
+            .map(Usage::normalize)
+    }
+}
+
+/// How many recently counted task IDs are remembered to avoid counting a task twice.
+pub(crate) const COUNTED_TASK_LIMIT: usize = 1024;
+
+#[derive(Default)]
+struct UsageState {
+    total: Usage,
+    counted: HashSet<TaskId>,
+    counted_order: VecDeque<TaskId>,
+    budget: Option<BudgetLedger>,
+}
+
+impl UsageState {
+    /// Remembers a task ID, forgetting the oldest past [`COUNTED_TASK_LIMIT`]. Returns
+    /// `false` if the task was already counted.
+    fn remember(&mut self, task_id: &TaskId) -> bool {
+        if !self.counted.insert(task_id.clone()) {
+            return false;
+        }
+        self.counted_order.push_back(task_id.clone());
+        if self.counted_order.len() > COUNTED_TASK_LIMIT {
+            if let Some(oldest) = self.counted_order.pop_front() {
+                self.counted.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Sums usage across every completed task observed by a client.
+#[derive(Default)]
+pub(crate) struct UsageTracker {
+    state: Mutex<UsageState>,
+}
+
+impl UsageTracker {
+    /// Adds the usage of a completed task, counting each recently seen task ID at most once.
+    pub(crate) fn record(&self, status: &TaskStatus) -> Option<Usage> {
+        if status.status != TaskStatusValue::Completed {
+            return None;
+        }
+        let usage = status.usage()?;
+        let threshold = {
+            let mut state = self.state.lock().unwrap();
+            if !state.remember(&status.task_id) {
+                return None;
+            }
+            state.total.add(&usage);
+            state.budget.as_mut().and_then(|b| b.record(&usage))
+        };
+        if let Some((callback, budget_status)) = threshold {
+            callback(&budget_status);
+        }
+        Some(usage)
+    }
+
+    pub(crate) fn set_budget(&self, budget: Budget) {
+        self.state.lock().unwrap().budget = Some(BudgetLedger::new(budget));
+    }
+
+    pub(crate) fn budget_status(&self) -> Option<BudgetStatus> {
+        self.state
+            .lock()
+            .unwrap()
+            .budget
+            .as_mut()
+            .map(|b| b.status())
+    }
+
+    /// Fails if the configured budget has been exhausted.
+    pub(crate) fn check_budget(&self) -> Result<(), TaskForceAIError> {
+        match self.state.lock().unwrap().budget.as_mut() {
+            Some(budget) => budget.check(),
+            None => Ok(()),
+        }
+    }
+
+    fn snapshot(&self) -> Usage {
+        self.state.lock().unwrap().total.clone()
+    }
+
+    fn reset(&self) -> Usage {
+        std::mem::take(&mut self.state.lock().unwrap().total)
+    }
+}
+
+impl TaskForceAI {
+    /// Returns the usage summed across all tasks completed through this client.
+    pub fn usage(&self) -> Usage {
+        self.usage.snapshot()
+    }
+
+    /// Resets the accumulated usage, returning the totals up to this point.
+    /// Recently counted tasks are not counted again, and any budget keeps its own spend.
+    pub fn reset_usage(&self) -> Usage {
+        self.usage.reset()
+    }
+}